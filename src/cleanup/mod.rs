@@ -0,0 +1,313 @@
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::yap_cli::yap_cli::Song;
+use crate::Cleanup;
+
+/// Built-in rules for the boilerplate YouTube adds to titles.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// "(Official Video)", "[Official Music Video]", "Official Audio", ...
+    Official,
+    /// "(Lyrics)", "[Lyric Video]", "(with lyrics)"
+    Lyrics,
+    /// "(HD)", "[4K]", "(1080p)", "(Remastered 2011)"
+    Quality,
+    /// "Artist - Title" when the artist field already is "Artist".
+    ArtistPrefix,
+    /// Artist names of auto-generated channels: "Artist - Topic", "ArtistVEVO".
+    Channel,
+}
+
+/// Which part of a song a custom rule rewrites.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    #[default]
+    Title,
+    Artist,
+}
+
+/// A regex replacement configured in the `cleanup` section.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Rule {
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default)]
+    pub field: Field,
+}
+
+struct Cleaner {
+    title_rules: Vec<(Regex, String)>,
+    artist_rules: Vec<(Regex, String)>,
+    artist_prefix: bool,
+}
+
+static CLEANER: OnceLock<Cleaner> = OnceLock::new();
+
+/// Compiles the presets and rules from the config. Until this is called
+/// `clean` returns songs unchanged.
+pub fn init(settings: &Cleanup) {
+    let _ = CLEANER.set(Cleaner::new(settings));
+}
+
+/// Returns the title and artist to display for `song`.
+pub fn clean(song: &Song) -> Song {
+    match CLEANER.get() {
+        Some(cleaner) => cleaner.clean(song),
+        None => song.clone(),
+    }
+}
+
+impl Cleaner {
+    fn new(settings: &Cleanup) -> Cleaner {
+        let mut cleaner = Cleaner {
+            title_rules: vec![],
+            artist_rules: vec![],
+            artist_prefix: false,
+        };
+        for preset in &settings.presets {
+            let (field, patterns): (Field, &[&str]) = match preset {
+                Preset::Official => (
+                    Field::Title,
+                    &[
+                        r"(?i)\s*[(\[]\s*official\b[^)\]]*[)\]]",
+                        r"(?i)\s+official\s+(music\s+|lyric\s+)?(video|audio|visualizer)\b",
+                    ],
+                ),
+                Preset::Lyrics => (
+                    Field::Title,
+                    &[r"(?i)\s*[(\[]\s*(with\s+)?lyrics?(\s+video)?\s*[)\]]"],
+                ),
+                Preset::Quality => (
+                    Field::Title,
+                    &[
+                        r"(?i)\s*[(\[]\s*(hd|hq|4k|8k|\d{3,4}p|remaster(ed)?(\s+\d{4})?)\s*[)\]]",
+                        r"(?i)\s+(hd|hq|4k)\s*$",
+                    ],
+                ),
+                Preset::Channel => (Field::Artist, &[r"(?i)\s+-\s+topic\s*$", r"VEVO\s*$"]),
+                Preset::ArtistPrefix => {
+                    cleaner.artist_prefix = true;
+                    continue;
+                }
+            };
+            for pattern in patterns {
+                cleaner.add(field, pattern, "");
+            }
+        }
+        for rule in &settings.rules {
+            cleaner.add(rule.field, &rule.pattern, &rule.replacement);
+        }
+        cleaner
+    }
+
+    fn add(&mut self, field: Field, pattern: &str, replacement: &str) {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(error) => {
+                println!("Ignoring clean-up rule {}: {}", pattern, error);
+                return;
+            }
+        };
+        let rules = match field {
+            Field::Title => &mut self.title_rules,
+            Field::Artist => &mut self.artist_rules,
+        };
+        rules.push((regex, replacement.to_string()));
+    }
+
+    fn clean(&self, song: &Song) -> Song {
+        let artist = apply(&self.artist_rules, &song.artist);
+        let mut name = apply(&self.title_rules, &song.name);
+        if self.artist_prefix && !artist.is_empty() {
+            let prefix = format!("{} - ", artist);
+            let matches = name
+                .get(..prefix.len())
+                .is_some_and(|start| start.to_lowercase() == prefix.to_lowercase());
+            if matches {
+                name = name[prefix.len()..].trim().to_string();
+            }
+        }
+        // Never clean a title away entirely.
+        if name.is_empty() {
+            name = song.name.clone();
+        }
+        Song { name, artist }
+    }
+}
+
+/// Runs `rules` over `value`. The spacing and separators left behind are only
+/// tidied up when a rule matched, and a value cleaned away entirely is kept.
+fn apply(rules: &[(Regex, String)], value: &str) -> String {
+    let mut cleaned = value.to_string();
+    let mut matched = false;
+    for (regex, replacement) in rules {
+        if let Cow::Owned(replaced) = regex.replace_all(&cleaned, replacement.as_str()) {
+            cleaned = replaced;
+            matched = true;
+        }
+    }
+    if !matched {
+        return cleaned;
+    }
+    let cleaned = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(|c: char| c == '-' || c == '|' || c.is_whitespace())
+        .to_string();
+    if cleaned.is_empty() {
+        value.to_string()
+    } else {
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(presets: &[Preset], rules: Vec<Rule>, name: &str, artist: &str) -> (String, String) {
+        let cleaner = Cleaner::new(&Cleanup {
+            presets: presets.to_vec(),
+            rules,
+        });
+        let song = cleaner.clean(&Song {
+            name: name.to_string(),
+            artist: artist.to_string(),
+        });
+        (song.name, song.artist)
+    }
+
+    fn rule(field: Field, pattern: &str) -> Rule {
+        Rule {
+            pattern: pattern.to_string(),
+            replacement: String::new(),
+            field,
+        }
+    }
+
+    #[test]
+    fn presets_clean_titles_and_artists() {
+        let cases = [
+            (
+                Preset::Official,
+                "Song (Official Video)",
+                "Band",
+                "Song",
+                "Band",
+            ),
+            (
+                Preset::Official,
+                "Song [Official Music Video]",
+                "Band",
+                "Song",
+                "Band",
+            ),
+            (
+                Preset::Official,
+                "Song Official Audio",
+                "Band",
+                "Song",
+                "Band",
+            ),
+            (
+                Preset::Official,
+                "Song | Official Visualizer",
+                "Band",
+                "Song",
+                "Band",
+            ),
+            (Preset::Lyrics, "Song (Lyrics)", "Band", "Song", "Band"),
+            (Preset::Lyrics, "Song [Lyric Video]", "Band", "Song", "Band"),
+            (Preset::Lyrics, "Song (with lyrics)", "Band", "Song", "Band"),
+            (Preset::Quality, "Song (HD)", "Band", "Song", "Band"),
+            (Preset::Quality, "Song [4K]", "Band", "Song", "Band"),
+            (Preset::Quality, "Song (1080p)", "Band", "Song", "Band"),
+            (
+                Preset::Quality,
+                "Song (Remastered 2011)",
+                "Band",
+                "Song",
+                "Band",
+            ),
+            (Preset::Quality, "Song HQ", "Band", "Song", "Band"),
+            (Preset::ArtistPrefix, "Band - Song", "Band", "Song", "Band"),
+            (Preset::ArtistPrefix, "band - Song", "Band", "Song", "Band"),
+            (Preset::Channel, "Song", "Band - Topic", "Song", "Band"),
+            (Preset::Channel, "Song", "BandVEVO", "Song", "Band"),
+        ];
+        for (preset, name, artist, expected_name, expected_artist) in cases {
+            assert_eq!(
+                clean(&[preset], vec![], name, artist),
+                (expected_name.to_string(), expected_artist.to_string()),
+                "{:?} on {} by {}",
+                preset,
+                name,
+                artist
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_values_without_matches_untouched() {
+        let all = [
+            Preset::Official,
+            Preset::Lyrics,
+            Preset::Quality,
+            Preset::ArtistPrefix,
+            Preset::Channel,
+        ];
+        let cases = [
+            (&all[..], "Official  Secrets -", "Band |"),
+            (&all[..], "Bandit - Song", "Band"),
+            (&[][..], "Song (Official Video)  -", "Band - Topic"),
+        ];
+        for (presets, name, artist) in cases {
+            assert_eq!(
+                clean(presets, vec![], name, artist),
+                (name.to_string(), artist.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn never_cleans_a_field_away() {
+        let rules = vec![rule(Field::Title, "(?i)^song$"), rule(Field::Artist, ".*")];
+        assert_eq!(
+            clean(&[], rules, "Song", "Band"),
+            ("Song".to_string(), "Band".to_string())
+        );
+        assert_eq!(
+            clean(&[Preset::Channel], vec![], "Song", "VEVO"),
+            ("Song".to_string(), "VEVO".to_string())
+        );
+        assert_eq!(
+            clean(&[Preset::ArtistPrefix], vec![], "Band - ", "Band"),
+            ("Band - ".to_string(), "Band".to_string())
+        );
+    }
+
+    #[test]
+    fn runs_custom_rules_after_presets() {
+        let rules = vec![
+            rule(Field::Title, "("),
+            rule(Field::Title, r"\s*\(feat\. [^)]*\)$"),
+        ];
+        assert_eq!(
+            clean(
+                &[Preset::Official],
+                rules,
+                "Song (feat. Singer) (Official Video)",
+                "Band"
+            ),
+            ("Song".to_string(), "Band".to_string())
+        );
+    }
+}
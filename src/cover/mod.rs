@@ -0,0 +1,160 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use gtk::cairo::{Context, FontSlant, FontWeight, Format, ImageSurface};
+use lofty::{MimeType, PictureType, TaggedFileExt};
+
+use crate::thumbnails;
+use crate::trash::song_files;
+
+/// Size of the generated placeholder tiles, before thumbnailing.
+const PLACEHOLDER_SIZE: i32 = 256;
+
+/// Where the art of a song can come from. Resolving it may read the audio
+/// file, so song rows do it off the main thread.
+#[derive(Clone, Debug)]
+pub struct ArtSource {
+    /// The miniature downloaded by yap, if there is one.
+    pub miniature: Option<PathBuf>,
+    /// The audio file, when the library knows it.
+    pub audio: Option<PathBuf>,
+    pub music_directory: PathBuf,
+    pub title: String,
+}
+
+impl ArtSource {
+    /// Returns the miniature, else the cover art embedded in the audio file,
+    /// else a placeholder tile with the initials of the title.
+    pub fn resolve(&self) -> Option<PathBuf> {
+        self.miniature
+            .clone()
+            .or_else(|| {
+                let audio = self.audio.clone().or_else(|| {
+                    song_files(&self.music_directory, &self.title)
+                        .into_iter()
+                        .find(|path| {
+                            let extension = path.extension().and_then(|e| e.to_str());
+                            !extension.is_some_and(|e| thumbnails::EXTENSIONS.contains(&e))
+                        })
+                })?;
+                embedded(&audio)
+            })
+            .or_else(|| placeholder(&self.title))
+    }
+}
+
+fn cache_directory(kind: &str) -> Option<PathBuf> {
+    let directory = dirs::cache_dir()?.join("yap").join(kind);
+    fs::create_dir_all(&directory).ok()?;
+    Some(directory)
+}
+
+/// Extracts the front cover from ID3 APIC frames, FLAC pictures, MP4 `covr`
+/// atoms and Vorbis/Opus METADATA_BLOCK_PICTURE comments. Covers are cached
+/// by a hash of the full path, as files in different folders share names.
+pub fn embedded(audio: &Path) -> Option<PathBuf> {
+    let name = format!("{:016x}", fnv1a(&audio.to_string_lossy()));
+    let directory = cache_directory("covers")?;
+    if let Some(cached) = ["jpg", "png"]
+        .iter()
+        .map(|extension| directory.join(format!("{}.{}", name, extension)))
+        .find(|path| is_newer(path, audio))
+    {
+        return Some(cached);
+    }
+    let file = lofty::read_from_path(audio).ok()?;
+    let pictures: Vec<_> = file.tags().iter().flat_map(|tag| tag.pictures()).collect();
+    let picture = pictures
+        .iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first())?;
+    let extension = match picture.mime_type() {
+        Some(MimeType::Png) => "png",
+        _ => "jpg",
+    };
+    let path = directory.join(format!("{}.{}", name, extension));
+    match fs::write(&path, picture.data()) {
+        Ok(()) => Some(path),
+        Err(error) => {
+            println!("Couldn't save cover of {}: {}", audio.display(), error);
+            None
+        }
+    }
+}
+
+fn is_newer(path: &Path, than: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(path), modified(than)) {
+        (Ok(path), Ok(than)) => path >= than,
+        _ => false,
+    }
+}
+
+/// Draws the initials of `title` on a background whose color is derived from
+/// the title, so a song always gets the same tile.
+pub fn placeholder(title: &str) -> Option<PathBuf> {
+    let hash = fnv1a(title);
+    let path = cache_directory("placeholders")?.join(format!("{:016x}.png", hash));
+    if path.is_file() {
+        return Some(path);
+    }
+    let initials: String = title
+        .split_whitespace()
+        .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect();
+
+    let surface = ImageSurface::create(Format::ARgb32, PLACEHOLDER_SIZE, PLACEHOLDER_SIZE).ok()?;
+    let context = Context::new(&surface).ok()?;
+    let (red, green, blue) = hsl_to_rgb((hash % 360) as f64, 0.45, 0.4);
+    context.set_source_rgb(red, green, blue);
+    context.paint().ok()?;
+
+    let size = PLACEHOLDER_SIZE as f64;
+    context.select_font_face("Sans", FontSlant::Normal, FontWeight::Bold);
+    context.set_font_size(size * 0.4);
+    context.set_source_rgb(1., 1., 1.);
+    if let Ok(extents) = context.text_extents(&initials) {
+        context.move_to(
+            (size - extents.width()) / 2. - extents.x_bearing(),
+            (size - extents.height()) / 2. - extents.y_bearing(),
+        );
+        context.show_text(&initials).ok()?;
+    }
+    drop(context);
+
+    let mut file = File::create(&path).ok()?;
+    match surface.write_to_png(&mut file) {
+        Ok(()) => Some(path),
+        Err(error) => {
+            println!("Couldn't save placeholder {}: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+/// A hash that stays the same across runs and Rust versions, unlike
+/// `DefaultHasher`.
+pub fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64) {
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let x = chroma * (1. - ((hue / 60.) % 2. - 1.).abs());
+    let m = lightness - chroma / 2.;
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    (r + m, g + m, b + m)
+}
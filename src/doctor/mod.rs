@@ -0,0 +1,234 @@
+use std::env;
+
+use gtk::pango;
+use gtk::prelude::*;
+
+use crate::glyphs;
+use crate::library::Library;
+use crate::mpd::Mpd;
+use crate::volume;
+use crate::yap_cli::yap_cli::Yap;
+use crate::{expand_home, Config};
+
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    pub hint: String,
+}
+
+impl Check {
+    fn new(name: &'static str, result: Result<String, (String, String)>) -> Check {
+        match result {
+            Ok(detail) => Check {
+                name,
+                passed: true,
+                detail,
+                hint: String::new(),
+            },
+            Err((detail, hint)) => Check {
+                name,
+                passed: false,
+                detail,
+                hint,
+            },
+        }
+    }
+}
+
+/// Runs every check. The font and layer-shell checks need GTK to be
+/// initialized and fail otherwise.
+pub fn run(config: &Config) -> Vec<Check> {
+    let mut checks = vec![
+        binary(
+            "yap",
+            "Install youtube-audio-player and make sure `yap` is in PATH.",
+        ),
+        binary(
+            "mpd",
+            "Install MPD to play music and restart it from the power menu.",
+        ),
+        volume_backend(),
+        directory("Music directory", &config.general.music_directory),
+        directory("Miniature directory", &config.general.miniature_directory),
+        yap_flags(),
+        database(&config.database.database_path),
+        mpd(),
+    ];
+    if config.general.download_miniature {
+        checks.push(miniature_command(&config.general.miniature_command));
+    }
+    if config.supervisor.use_systemd {
+        checks.push(binary(
+            "systemctl",
+            "Disable `use_systemd` in the supervisor section of the config.",
+        ));
+    }
+    checks.push(font());
+    checks.push(layer_shell());
+    checks
+}
+
+/// Prints the checks for `--doctor` and returns whether all of them passed.
+pub fn print_report(checks: &[Check]) -> bool {
+    for check in checks {
+        println!(
+            "[{}] {}: {}",
+            if check.passed { "PASS" } else { "FAIL" },
+            check.name,
+            check.detail
+        );
+        if !check.passed {
+            println!("       {}", check.hint);
+        }
+    }
+    checks.iter().all(|check| check.passed)
+}
+
+fn binary(name: &'static str, hint: &str) -> Check {
+    let found = env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|path| path.join(name))
+            .find(|path| path.is_file())
+    });
+    Check::new(
+        name,
+        match found {
+            Some(path) => Ok(path.display().to_string()),
+            None => Err(("not found in PATH".to_string(), hint.to_string())),
+        },
+    )
+}
+
+/// `pamixer` is only the fallback, so it's only required when neither
+/// PulseAudio nor ALSA can be reached directly.
+fn volume_backend() -> Check {
+    match volume::detect().name() {
+        "pamixer" => binary(
+            "pamixer",
+            "Install pamixer, or make PulseAudio or ALSA reachable, to control the volume.",
+        ),
+        backend => Check::new("pamixer", Ok(format!("not needed, {} is used", backend))),
+    }
+}
+
+fn miniature_command(program: &str) -> Check {
+    Check::new(
+        "Miniature command",
+        if Yap::supports_miniatures(program) {
+            Ok(format!("{} song miniature --name", program))
+        } else {
+            Err((
+                format!("{} has no `song miniature --name` subcommand", program),
+                "Set `miniature_command` to a script taking `song miniature --name <song>`, or disable `download_miniature`.".to_string(),
+            ))
+        },
+    )
+}
+
+fn yap_flags() -> Check {
+    let missing = Yap::missing_flags();
+    Check::new(
+        "yap version",
+        if missing.is_empty() {
+            Ok("supports every flag the GUI uses".to_string())
+        } else {
+            Err((
+                format!("missing {}", missing.join(", ")),
+                "Update youtube-audio-player; queue and song editing won't work until then."
+                    .to_string(),
+            ))
+        },
+    )
+}
+
+fn directory(name: &'static str, path: &str) -> Check {
+    let expanded = expand_home(path);
+    Check::new(
+        name,
+        if expanded.is_dir() {
+            Ok(expanded.display().to_string())
+        } else {
+            Err((
+                format!("{} doesn't exist", expanded.display()),
+                "Create it or change the path in yap.config.".to_string(),
+            ))
+        },
+    )
+}
+
+fn database(path: &str) -> Check {
+    let expanded = expand_home(path);
+    Check::new(
+        "Database",
+        if expanded.is_file() {
+            match Library::open(&expanded) {
+                Ok(library) => Ok(format!(
+                    "{} (schema version {})",
+                    expanded.display(),
+                    library.schema_version()
+                )),
+                Err(error) => Err((
+                    error,
+                    "The GUI will fall back to listing songs through `yap`.".to_string(),
+                )),
+            }
+        } else {
+            Err((
+                format!("{} doesn't exist", expanded.display()),
+                "Run `yap` once to create the database or fix `database_path`.".to_string(),
+            ))
+        },
+    )
+}
+
+fn mpd() -> Check {
+    Check::new(
+        "MPD connection",
+        match Mpd::connect() {
+            Ok(mpd) => Ok(format!("MPD {}", mpd.version)),
+            Err(error) => Err((
+                error,
+                "Start MPD or check MPD_HOST/MPD_PORT and the servers in yap.config.".to_string(),
+            )),
+        },
+    )
+}
+
+fn font() -> Check {
+    let result = if gtk::is_initialized_main_thread() {
+        let layout = pango::Layout::new(&gtk::Label::new(None).pango_context());
+        layout.set_text(&glyphs::ALL.concat());
+        match layout.unknown_glyphs_count() {
+            0 => Ok("all button glyphs are covered".to_string()),
+            missing => Err((
+                format!("{} button glyphs have no font", missing),
+                "Install a Nerd Font, e.g. Symbols Nerd Font.".to_string(),
+            )),
+        }
+    } else {
+        Err((
+            "GTK couldn't be initialized".to_string(),
+            "Run from a graphical session.".to_string(),
+        ))
+    };
+    Check::new("Nerd Font", result)
+}
+
+fn layer_shell() -> Check {
+    let result = if !gtk::is_initialized_main_thread() {
+        Err((
+            "GTK couldn't be initialized".to_string(),
+            "Run from a graphical session.".to_string(),
+        ))
+    } else if gtk4_layer_shell::is_supported() {
+        Ok("supported by the compositor".to_string())
+    } else {
+        Err((
+            "not supported by the compositor".to_string(),
+            "Use a Wayland compositor that implements wlr-layer-shell.".to_string(),
+        ))
+    };
+    Check::new("Layer shell", result)
+}
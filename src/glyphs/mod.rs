@@ -0,0 +1,60 @@
+pub const PLAY: &str = "";
+pub const PAUSE: &str = "󰏤";
+pub const PREVIOUS: &str = "󰒮";
+pub const NEXT: &str = "󰒭";
+pub const PREVIOUS_CHAPTER: &str = "󰙣";
+pub const NEXT_CHAPTER: &str = "󰙡";
+pub const SHUFFLE: &str = "󰒟";
+pub const REPEAT: &str = "󰑖";
+pub const CLEAR: &str = "";
+pub const RESTORE: &str = "󰦛";
+pub const SAVE: &str = "󰆓";
+pub const UP: &str = "󰁝";
+pub const DOWN: &str = "󰁅";
+pub const DELETE: &str = "󰆴";
+pub const ADD_TO_QUEUE: &str = "󰐒";
+pub const PLAY_NEXT: &str = "󰼛";
+pub const DETAILS: &str = "󰋽";
+pub const EDIT: &str = "󰏫";
+pub const RELOAD: &str = "󰑐";
+pub const CHECK: &str = "󰄬";
+pub const CLOSE: &str = "󰅖";
+pub const CORNER: &str = "󰩨";
+pub const POWER: &str = "";
+pub const OUTPUT: &str = "󰋋";
+pub const VOLUME: &str = "󰕾";
+pub const MUTED: &str = "󰝟";
+pub const SYSTEM_VOLUME: &str = "󰓃";
+pub const MPD_VOLUME: &str = "󰝚";
+
+/// Every glyph above, so `--doctor` can check that the fonts cover them.
+pub const ALL: [&str; 28] = [
+    PLAY,
+    PAUSE,
+    PREVIOUS,
+    NEXT,
+    PREVIOUS_CHAPTER,
+    NEXT_CHAPTER,
+    SHUFFLE,
+    REPEAT,
+    CLEAR,
+    RESTORE,
+    SAVE,
+    UP,
+    DOWN,
+    DELETE,
+    ADD_TO_QUEUE,
+    PLAY_NEXT,
+    DETAILS,
+    EDIT,
+    RELOAD,
+    CHECK,
+    CLOSE,
+    CORNER,
+    POWER,
+    OUTPUT,
+    VOLUME,
+    MUTED,
+    SYSTEM_VOLUME,
+    MPD_VOLUME,
+];
@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use gtk::glib;
+use lofty::{AudioFile, FileType, TaggedFileExt};
+
+use super::info::Info;
+use super::SongRecord;
+use crate::trash::song_files;
+
+/// Everything the details panel shows about a song.
+#[derive(Clone, Debug, Default)]
+pub struct SongDetails {
+    pub name: String,
+    pub artist: String,
+    pub path: Option<PathBuf>,
+    /// Duration in seconds.
+    pub duration: Option<u32>,
+    pub codec: Option<String>,
+    /// Bitrate in kbit/s.
+    pub bitrate: Option<u32>,
+    pub size: Option<u64>,
+    pub downloaded_at: Option<String>,
+    pub url: Option<String>,
+    pub video_id: Option<String>,
+    pub channel: Option<String>,
+    pub upload_date: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl SongDetails {
+    /// Reads the audio file and its sidecar, so this belongs off the main
+    /// thread. `sidecar` is used when there's no `.info.json` next to the
+    /// file.
+    pub fn load(
+        record: &SongRecord,
+        sidecar: Option<PathBuf>,
+        music_directory: &Path,
+    ) -> SongDetails {
+        // Only songs from a database without paths are looked up by name.
+        let path = match &record.path {
+            Some(path) => Some(music_directory.join(path)),
+            None => song_files(music_directory, &record.song.name)
+                .into_iter()
+                .next(),
+        };
+        let info = path
+            .as_ref()
+            .map(|path| path.with_extension("info.json"))
+            .filter(|path| path.is_file())
+            .or(sidecar)
+            .and_then(|path| Info::read(&path))
+            .unwrap_or_default();
+        let metadata = path.as_ref().and_then(|path| fs::metadata(path).ok());
+        let properties = path.as_deref().map(properties).unwrap_or_default();
+        let downloaded_at = record.downloaded_at.clone().or_else(|| {
+            let modified = metadata.as_ref()?.modified().ok()?;
+            let seconds = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+            glib::DateTime::from_unix_local(seconds as i64)
+                .and_then(|date| date.format("%Y-%m-%d %H:%M"))
+                .ok()
+                .map(|date| date.to_string())
+        });
+        let url = record.url.clone().or_else(|| info.webpage_url.clone());
+        let video_id = url
+            .as_deref()
+            .and_then(video_id_from_url)
+            .or_else(|| info.id.clone())
+            .or_else(|| path.as_deref().and_then(video_id_from_file_name));
+        SongDetails {
+            name: record.song.name.clone(),
+            artist: record.song.artist.clone(),
+            duration: record.duration.or(properties.duration),
+            codec: properties.codec,
+            bitrate: properties.bitrate,
+            size: metadata.map(|metadata| metadata.len()),
+            downloaded_at,
+            url: url.or_else(|| {
+                video_id
+                    .as_ref()
+                    .map(|id| format!("https://www.youtube.com/watch?v={}", id))
+            }),
+            video_id,
+            path,
+            upload_date: info.upload_date(),
+            channel: info.channel,
+            description: info.description,
+            tags: info.tags.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Properties {
+    duration: Option<u32>,
+    codec: Option<String>,
+    bitrate: Option<u32>,
+}
+
+/// Reads the codec, bitrate and duration of an audio file with lofty.
+fn properties(path: &Path) -> Properties {
+    let file = match lofty::read_from_path(path) {
+        Ok(file) => file,
+        Err(error) => {
+            println!("Couldn't read {}: {}", path.display(), error);
+            return Properties::default();
+        }
+    };
+    let properties = file.properties();
+    let duration = properties.duration().as_secs_f64().round() as u32;
+    let codec = match file.file_type() {
+        FileType::Mpeg => "MP3".to_string(),
+        FileType::Mp4 => "M4A".to_string(),
+        FileType::Aac => "AAC".to_string(),
+        FileType::Flac => "FLAC".to_string(),
+        FileType::Opus => "Opus".to_string(),
+        FileType::Vorbis => "Vorbis".to_string(),
+        FileType::Wav => "WAV".to_string(),
+        other => format!("{:?}", other),
+    };
+    Properties {
+        duration: (duration > 0).then_some(duration),
+        codec: Some(codec),
+        bitrate: properties
+            .audio_bitrate()
+            .or_else(|| properties.overall_bitrate()),
+    }
+}
+
+/// Extracts the video ID from `watch?v=`, `youtu.be/` and `shorts/` URLs.
+pub fn video_id_from_url(url: &str) -> Option<String> {
+    let id = if let Some((_, query)) = url.split_once('?') {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("v="))
+            .or_else(|| url.split_once("youtu.be/").map(|(_, rest)| rest))
+    } else {
+        url.split_once("youtu.be/")
+            .or_else(|| url.split_once("/shorts/"))
+            .map(|(_, rest)| rest)
+    }?;
+    let id: String = id
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    (!id.is_empty()).then_some(id)
+}
+
+/// yt-dlp names files `title [id].ext` by default.
+fn video_id_from_file_name(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let id = stem.strip_suffix(']')?.rsplit_once('[')?.1;
+    (id.len() == 11).then(|| id.to_string())
+}
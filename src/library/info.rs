@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+/// The parts of a yt-dlp `.info.json` sidecar the GUI uses.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Info {
+    pub id: Option<String>,
+    pub webpage_url: Option<String>,
+    pub channel: Option<String>,
+    /// `YYYYMMDD`, as written by yt-dlp.
+    pub upload_date: Option<String>,
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub chapters: Option<Vec<Chapter>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Chapter {
+    /// Start of the chapter in seconds.
+    pub start_time: f64,
+    pub title: String,
+}
+
+impl Info {
+    pub fn read(path: &Path) -> Option<Info> {
+        let content = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(info) => Some(info),
+            Err(error) => {
+                println!("Couldn't read {}: {}", path.display(), error);
+                None
+            }
+        }
+    }
+
+    pub fn upload_date(&self) -> Option<String> {
+        let date = self.upload_date.as_ref()?;
+        if date.len() != 8 {
+            return Some(date.clone());
+        }
+        Some(format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]))
+    }
+}
+
+/// The `.info.json` files found in the music directory, keyed by the name of
+/// the song they belong to.
+#[derive(Default)]
+pub struct Sidecars {
+    directory: PathBuf,
+    paths: HashMap<String, PathBuf>,
+}
+
+impl Sidecars {
+    pub fn index(music_directory: &Path) -> Sidecars {
+        Sidecars {
+            directory: music_directory.to_path_buf(),
+            paths: paths(music_directory),
+        }
+    }
+
+    /// Indexes the music directory again, for sidecars written or renamed
+    /// since.
+    pub fn refresh(&mut self) {
+        self.paths = paths(&self.directory);
+    }
+
+    pub fn get(&self, song_name: &str) -> Option<Info> {
+        Info::read(self.path(song_name)?)
+    }
+
+    pub fn path(&self, song_name: &str) -> Option<&PathBuf> {
+        self.paths.get(song_name)
+    }
+
+    /// Moves the sidecar of `song_name` to `new_name`, keeping the video ID
+    /// in the file name.
+    pub fn rename(&mut self, song_name: &str, new_name: &str) -> Result<(), String> {
+        let Some(path) = self.paths.get(song_name) else {
+            return Ok(());
+        };
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            return Ok(());
+        };
+        let target = path.with_file_name(format!(
+            "{}{}",
+            new_name,
+            file_name.strip_prefix(song_name).unwrap_or(".info.json")
+        ));
+        fs::rename(path, &target)
+            .map_err(|error| format!("Couldn't move {}: {}", path.display(), error))?;
+        self.refresh();
+        Ok(())
+    }
+}
+
+/// The `.info.json` files of `music_directory` by song name.
+fn paths(music_directory: &Path) -> HashMap<String, PathBuf> {
+    let mut paths = HashMap::new();
+    let entries = match fs::read_dir(music_directory) {
+        Ok(entries) => entries,
+        Err(_) => return paths,
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".info.json"))
+        else {
+            continue;
+        };
+        // yt-dlp's default template appends the video ID: `title [id]`.
+        if let Some((title, _)) = name
+            .strip_suffix(']')
+            .and_then(|name| name.rsplit_once(" ["))
+        {
+            paths.insert(title.to_string(), path.clone());
+        }
+        paths.insert(name.to_string(), path);
+    }
+    paths
+}
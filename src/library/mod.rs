@@ -0,0 +1,405 @@
+pub mod details;
+pub mod info;
+pub mod tags;
+
+use std::fs;
+use std::path::Path;
+
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+
+use self::info::Sidecars;
+
+use crate::thumbnails::miniature_path;
+use crate::yap_cli::yap_cli::{Song, Yap};
+use crate::Database;
+
+/// A song with the metadata yap stores next to it. Fields the database
+/// doesn't have are `None`.
+#[derive(Clone, Debug)]
+pub struct SongRecord {
+    pub song: Song,
+    /// Path of the audio file, relative to the music directory.
+    pub path: Option<String>,
+    pub url: Option<String>,
+    /// Duration in seconds.
+    pub duration: Option<u32>,
+    pub downloaded_at: Option<String>,
+}
+
+impl From<Song> for SongRecord {
+    fn from(song: Song) -> Self {
+        SongRecord {
+            song,
+            path: None,
+            url: None,
+            duration: None,
+            downloaded_at: None,
+        }
+    }
+}
+
+/// Where one version of the yap database keeps the library.
+struct Schema {
+    version: i64,
+    songs: &'static str,
+    /// Columns of `songs` holding the name, artist, path, URL, duration and
+    /// download date, or `None` where the version doesn't store the field.
+    song_columns: [Option<&'static str>; 6],
+    playlists: &'static str,
+}
+
+/// The yap schemas this reader supports, by `PRAGMA user_version`. Version 1
+/// only has names and artists; version 2 adds the file path, source URL,
+/// duration and download date.
+static SCHEMAS: [Schema; 2] = [
+    Schema {
+        version: 1,
+        songs: "song",
+        song_columns: [Some("name"), Some("artist"), None, None, None, None],
+        playlists: "playlist",
+    },
+    Schema {
+        version: 2,
+        songs: "song",
+        song_columns: [
+            Some("name"),
+            Some("artist"),
+            Some("path"),
+            Some("url"),
+            Some("duration"),
+            Some("downloaded_at"),
+        ],
+        playlists: "playlist",
+    },
+];
+
+/// Read-only access to the yap SQLite database. Changes to the library still
+/// go through `yap`, which owns the database.
+pub struct Library {
+    connection: Connection,
+    schema: &'static Schema,
+}
+
+impl Library {
+    pub fn open(path: &Path) -> Result<Library, String> {
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|error| format!("Couldn't open {}: {}", path.display(), error))?;
+        Library::from_connection(connection)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Library, String> {
+        let schema = Schema::detect(&connection)?;
+        Ok(Library { connection, schema })
+    }
+
+    pub fn schema_version(&self) -> i64 {
+        self.schema.version
+    }
+
+    pub fn songs(&self) -> Result<Vec<SongRecord>, String> {
+        let columns: Vec<String> = self
+            .schema
+            .song_columns
+            .iter()
+            .map(|column| column.map_or("NULL".to_string(), |column| format!("\"{}\"", column)))
+            .collect();
+        let query = format!(
+            "SELECT {} FROM \"{}\" ORDER BY 1",
+            columns.join(", "),
+            self.schema.songs
+        );
+        let mut statement = self
+            .connection
+            .prepare(&query)
+            .map_err(|error| error.to_string())?;
+        // Cells are converted one by one, so a value of an unexpected type
+        // only loses that field instead of failing the whole library.
+        let rows = statement
+            .query_map([], |row| {
+                Ok(SongRecord {
+                    song: Song {
+                        name: text(row.get_ref(0)?).unwrap_or_default(),
+                        artist: text(row.get_ref(1)?).unwrap_or_default(),
+                    },
+                    path: text(row.get_ref(2)?),
+                    url: text(row.get_ref(3)?),
+                    duration: seconds(row.get_ref(4)?),
+                    downloaded_at: text(row.get_ref(5)?),
+                })
+            })
+            .map_err(|error| error.to_string())?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|error| error.to_string())
+    }
+
+    pub fn playlists(&self) -> Result<Vec<String>, String> {
+        let mut statement = self
+            .connection
+            .prepare(&format!(
+                "SELECT name FROM \"{}\" ORDER BY name",
+                self.schema.playlists
+            ))
+            .map_err(|error| error.to_string())?;
+        let rows = statement
+            .query_map([], |row| Ok(text(row.get_ref(0)?).unwrap_or_default()))
+            .map_err(|error| error.to_string())?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|error| error.to_string())
+    }
+}
+
+impl Schema {
+    /// Picks the schema of the database's version and checks that its tables
+    /// and columns are really there.
+    /// Databases that never set a version are recognised by their columns
+    /// instead, as the newest schema whose columns are all there.
+    fn detect(connection: &Connection) -> Result<&'static Schema, String> {
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|error| error.to_string())?;
+        if version == 0 {
+            return SCHEMAS
+                .iter()
+                .rev()
+                .find(|schema| schema.check(connection).is_ok())
+                .ok_or_else(|| "Unrecognised yap database without a schema version".to_string());
+        }
+        let schema = SCHEMAS
+            .iter()
+            .find(|schema| schema.version == version)
+            .ok_or_else(|| format!("Unsupported yap database schema version {}", version))?;
+        schema.check(connection)?;
+        Ok(schema)
+    }
+
+    fn check(&self, connection: &Connection) -> Result<(), String> {
+        let song_columns = columns(connection, self.songs)?;
+        let missing = self
+            .song_columns
+            .iter()
+            .flatten()
+            .find(|column| !song_columns.iter().any(|found| found == *column));
+        if let Some(missing) = missing {
+            return Err(format!(
+                "Schema version {} should have a {}.{} column",
+                self.version, self.songs, missing
+            ));
+        }
+        if !columns(connection, self.playlists)?
+            .iter()
+            .any(|found| found == "name")
+        {
+            return Err(format!(
+                "Schema version {} should have a {}.name column",
+                self.version, self.playlists
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn columns(connection: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut statement = connection
+        .prepare(&format!("PRAGMA table_info(\"{}\")", table))
+        .map_err(|error| error.to_string())?;
+    let rows = statement
+        .query_map([], |row| row.get(1))
+        .map_err(|error| error.to_string())?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|error| error.to_string())
+}
+
+fn text(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => None,
+        ValueRef::Integer(number) => Some(number.to_string()),
+        ValueRef::Real(number) => Some(number.to_string()),
+        ValueRef::Text(text) => Some(String::from_utf8_lossy(text).to_string()),
+    }
+}
+
+fn seconds(value: ValueRef) -> Option<u32> {
+    match value {
+        ValueRef::Integer(number) => u32::try_from(number).ok(),
+        ValueRef::Real(number) if number >= 0. => Some(number.round() as u32),
+        ValueRef::Text(text) => std::str::from_utf8(text).ok()?.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Lists the library from the database, falling back to `yap song list` when
+/// the database can't be read.
+pub fn songs(database: &Database) -> Vec<SongRecord> {
+    match Library::open(&database.path()).and_then(|library| library.songs()) {
+        Ok(songs) => songs,
+        Err(error) => {
+            if crate::is_verbose() {
+                println!("Reading songs through yap: {}", error);
+            }
+            Yap::get_songs().into_iter().map(SongRecord::from).collect()
+        }
+    }
+}
+
+/// Lists the playlists from the database, falling back to `yap playlist list`.
+pub fn playlists(database: &Database) -> Vec<String> {
+    match Library::open(&database.path()).and_then(|library| library.playlists()) {
+        Ok(playlists) => playlists,
+        Err(error) => {
+            if crate::is_verbose() {
+                println!("Reading playlists through yap: {}", error);
+            }
+            Yap::get_playlists()
+        }
+    }
+}
+
+/// Moves the files that are found by a song's name after it's renamed in
+/// yap: the miniature and the `.info.json` sidecar.
+pub fn move_song_files(
+    song_name: &str,
+    new_name: &str,
+    miniature_directory: &Path,
+    sidecars: &mut Sidecars,
+) -> Result<(), String> {
+    if song_name == new_name {
+        return Ok(());
+    }
+    if let Some(miniature) = miniature_path(miniature_directory, song_name) {
+        let extension = miniature.extension().unwrap_or_default().to_string_lossy();
+        let target = miniature_directory.join(format!("{}.{}", new_name, extension));
+        fs::rename(&miniature, &target)
+            .map_err(|error| format!("Couldn't move {}: {}", miniature.display(), error))?;
+    }
+    sidecars.rename(song_name, new_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An in-memory database laid out like yap's schema `version`.
+    fn fixture(version: i64) -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        let songs = match version {
+            1 => "CREATE TABLE song (id INTEGER PRIMARY KEY, name TEXT, artist TEXT)",
+            _ => {
+                "CREATE TABLE song (id INTEGER PRIMARY KEY, name TEXT, artist TEXT, path TEXT,
+                    url TEXT, duration INTEGER, downloaded_at TEXT)"
+            }
+        };
+        connection
+            .execute_batch(&format!(
+                "{};
+                CREATE TABLE playlist (id INTEGER PRIMARY KEY, name TEXT);
+                INSERT INTO playlist (name) VALUES ('Focus'), ('Chill');
+                PRAGMA user_version = {};",
+                songs, version
+            ))
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn reads_every_field_of_version_2() {
+        let connection = fixture(2);
+        connection
+            .execute_batch(
+                "INSERT INTO song (name, artist, path, url, duration, downloaded_at) VALUES
+                    ('B side', 'Band', 'B side.mp3', 'https://example.com/b', 215, '2024-03-01'),
+                    ('A side', 'Band', NULL, NULL, NULL, NULL);",
+            )
+            .unwrap();
+        let library = Library::from_connection(connection).unwrap();
+        let songs = library.songs().unwrap();
+        assert_eq!(library.schema_version(), 2);
+        assert_eq!(songs.len(), 2);
+        assert_eq!(songs[0].song.name, "A side");
+        assert_eq!(songs[0].path, None);
+        assert_eq!(songs[1].path.as_deref(), Some("B side.mp3"));
+        assert_eq!(songs[1].url.as_deref(), Some("https://example.com/b"));
+        assert_eq!(songs[1].duration, Some(215));
+        assert_eq!(songs[1].downloaded_at.as_deref(), Some("2024-03-01"));
+        assert_eq!(library.playlists().unwrap(), vec!["Chill", "Focus"]);
+    }
+
+    #[test]
+    fn reads_names_and_artists_of_version_1() {
+        let connection = fixture(1);
+        connection
+            .execute_batch("INSERT INTO song (name, artist) VALUES ('Song', 'Band');")
+            .unwrap();
+        let songs = Library::from_connection(connection)
+            .unwrap()
+            .songs()
+            .unwrap();
+        assert_eq!(songs[0].song.artist, "Band");
+        assert_eq!(songs[0].duration, None);
+    }
+
+    #[test]
+    fn keeps_songs_whose_cells_have_unexpected_types() {
+        let connection = fixture(2);
+        connection
+            .execute_batch(
+                "INSERT INTO song (name, artist, duration) VALUES
+                    ('Real', 'Band', 61.6), ('Text', 'Band', '90'), ('Bad', 'Band', 'long');",
+            )
+            .unwrap();
+        let songs = Library::from_connection(connection)
+            .unwrap()
+            .songs()
+            .unwrap();
+        let durations: Vec<_> = songs.iter().map(|record| record.duration).collect();
+        assert_eq!(durations, vec![None, Some(62), Some(90)]);
+    }
+
+    #[test]
+    fn moves_the_miniature_and_sidecar_along() {
+        let directory = std::env::temp_dir().join(format!("yap-rename-{}", std::process::id()));
+        let miniatures = directory.join("miniatures");
+        fs::create_dir_all(&miniatures).unwrap();
+        fs::write(miniatures.join("Old (Official Video).webp"), "").unwrap();
+        fs::write(
+            directory.join("Old (Official Video) [dQw4w9WgXcQ].info.json"),
+            "{}",
+        )
+        .unwrap();
+        let mut sidecars = Sidecars::index(&directory);
+        move_song_files("Old (Official Video)", "Old", &miniatures, &mut sidecars).unwrap();
+        assert!(miniatures.join("Old.webp").is_file());
+        assert!(directory.join("Old [dQw4w9WgXcQ].info.json").is_file());
+        assert!(sidecars.get("Old").is_some());
+        assert!(sidecars.get("Old (Official Video)").is_none());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn detects_unversioned_databases_by_their_columns() {
+        let library = Library::from_connection(fixture(0)).unwrap();
+        assert_eq!(library.schema_version(), 2);
+        let connection = fixture(1);
+        connection
+            .execute_batch("PRAGMA user_version = 0;")
+            .unwrap();
+        let library = Library::from_connection(connection).unwrap();
+        assert_eq!(library.schema_version(), 1);
+        let connection = Connection::open_in_memory().unwrap();
+        assert!(Library::from_connection(connection).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_missing_columns() {
+        assert!(Library::from_connection(fixture(3)).is_err());
+        let connection = fixture(2);
+        connection
+            .execute_batch("ALTER TABLE song DROP COLUMN url;")
+            .unwrap();
+        let error = Library::from_connection(connection).err().unwrap();
+        assert!(error.contains("song.url"), "{}", error);
+    }
+}
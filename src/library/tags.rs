@@ -0,0 +1,23 @@
+use std::path::Path;
+
+use lofty::{Accessor, Probe, Tag, TagExt, TaggedFileExt};
+
+use crate::yap_cli::yap_cli::Song;
+
+/// Writes the title and artist of `song` into the tags of the audio file,
+/// using ID3v2 for MP3 and Vorbis comments for Opus, Vorbis and FLAC.
+pub fn write(path: &Path, song: &Song) -> Result<(), String> {
+    let mut file = Probe::open(path)
+        .and_then(|probe| probe.read())
+        .map_err(|error| format!("Couldn't read {}: {}", path.display(), error))?;
+    if file.primary_tag().is_none() {
+        file.insert_tag(Tag::new(file.primary_tag_type()));
+    }
+    let tag = file
+        .primary_tag_mut()
+        .ok_or_else(|| format!("{} can't hold tags", path.display()))?;
+    tag.set_title(song.name.clone());
+    tag.set_artist(song.artist.clone());
+    tag.save_to_path(path)
+        .map_err(|error| format!("Couldn't write tags to {}: {}", path.display(), error))
+}
@@ -1,28 +1,232 @@
+mod cleanup;
+mod cover;
+mod doctor;
+mod glyphs;
+mod library;
+mod miniatures;
+mod mpd;
+mod remote;
+mod state;
+mod status;
+mod thumbnails;
+mod trash;
+mod volume;
+mod web;
 mod widgets;
 mod yap_cli;
 
+use std::{
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use clap::{Parser, Subcommand};
+use gtk::prelude::*;
 use relm4::RelmApp;
+use widgets::mini_player_widget::MiniPlayerModel;
+use widgets::standalone_widget::{StandaloneModel, StandaloneWidget};
 use widgets::yap_widget::YapModel;
+use yap_cli::yap_cli::Yap;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Config {
     pub general: General,
     pub database: Database,
+    #[serde(default)]
+    pub volume: Volume,
+    #[serde(default)]
+    pub supervisor: Supervisor,
+    /// Skipped when empty: TOML can't write the empty array after the
+    /// tables above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<Server>,
+    #[serde(default)]
+    pub web: Web,
+    #[serde(default)]
+    pub cleanup: Cleanup,
+    #[serde(skip)]
+    pub runtime: Runtime,
 }
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct General {
     pub music_directory: String,
     pub miniature_directory: String,
     pub download_miniature: bool,
+    /// Program run as `<command> song miniature --name <song>` to fetch
+    /// missing miniatures when `download_miniature` is set. Not every yap
+    /// release has that subcommand, which `--doctor` checks; point this at a
+    /// wrapper script to fetch miniatures another way.
+    #[serde(default = "default_miniature_command")]
+    pub miniature_command: String,
+    #[serde(default)]
+    pub play_keeps_queue: bool,
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u64,
+    /// Whether editing a song also writes the title and artist into the tags
+    /// of its audio file by default.
+    #[serde(default)]
+    pub write_tags: bool,
+}
+
+fn default_miniature_command() -> String {
+    "yap".to_string()
+}
+
+fn default_trash_retention_days() -> u64 {
+    30
+}
+
+impl General {
+    pub fn music_directory(&self) -> PathBuf {
+        expand_home(&self.music_directory)
+    }
+
+    pub fn miniature_directory(&self) -> PathBuf {
+        expand_home(&self.miniature_directory)
+    }
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Database {
     pub database_path: String,
 }
 
+impl Database {
+    pub fn path(&self) -> PathBuf {
+        expand_home(&self.database_path)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Volume {
+    #[serde(default)]
+    pub target: VolumeTarget,
+    #[serde(default = "default_scroll_step")]
+    pub scroll_step: f64,
+    #[serde(default)]
+    pub show_percentage: bool,
+}
+
+fn default_scroll_step() -> f64 {
+    5.
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self {
+            target: VolumeTarget::default(),
+            scroll_step: default_scroll_step(),
+            show_percentage: false,
+        }
+    }
+}
+
+/// What the volume slider controls: the system master channel or MPD's own
+/// per-stream volume.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeTarget {
+    #[default]
+    Master,
+    Mpd,
+}
+
+/// How the power menu starts and stops MPD.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Supervisor {
+    #[serde(default)]
+    pub use_systemd: bool,
+    pub pid_file: Option<String>,
+    #[serde(default = "default_mpd_log_file")]
+    pub log_file: String,
+}
+
+fn default_mpd_log_file() -> String {
+    "~/.local/state/yap/mpd.log".to_string()
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self {
+            use_systemd: false,
+            pid_file: None,
+            log_file: default_mpd_log_file(),
+        }
+    }
+}
+
+/// An MPD instance the GUI can control, reached either over TCP or through a
+/// local socket.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Server {
+    pub name: String,
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub socket: Option<String>,
+    pub password: Option<String>,
+}
+
+fn default_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_port() -> u16 {
+    6600
+}
+
+impl Server {
+    /// Whether the server runs on this machine and can be supervised.
+    pub fn is_local(&self) -> bool {
+        self.socket.is_some() || self.host == "localhost" || self.host == "127.0.0.1"
+    }
+}
+
+/// Rules that turn YouTube titles into clean display names. The library keeps
+/// the original names unless they are rewritten from the clean-up page.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Cleanup {
+    #[serde(default)]
+    pub presets: Vec<cleanup::Preset>,
+    #[serde(default)]
+    pub rules: Vec<cleanup::Rule>,
+}
+
+/// The optional web remote served from the GUI process.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Web {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_web_address")]
+    pub address: String,
+    pub token: Option<String>,
+}
+
+fn default_web_address() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+impl Default for Web {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: default_web_address(),
+            token: None,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -30,25 +234,212 @@ impl Default for Config {
                 music_directory: "~/Music/songs/".to_string(),
                 miniature_directory: "~/Music/miniatures".to_string(),
                 download_miniature: false,
+                miniature_command: default_miniature_command(),
+                play_keeps_queue: false,
+                trash_retention_days: default_trash_retention_days(),
+                write_tags: false,
             },
             database: Database {
                 database_path: "~/.config/yap/yap.db".to_string(),
             },
+            volume: Volume::default(),
+            supervisor: Supervisor::default(),
+            servers: vec![],
+            web: Web::default(),
+            cleanup: Cleanup::default(),
+            runtime: Runtime::default(),
+        }
+    }
+}
+
+/// Settings given on the command line that only apply to this run.
+#[derive(Clone, Default)]
+pub struct Runtime {
+    pub window_mode: bool,
+}
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+#[derive(Parser)]
+#[command(about = "Overlay GUI for youtube-audio-player")]
+struct Cli {
+    /// Use this config file instead of the default yap.config
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Name of the MPD server from `servers` to connect to
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+    /// Launch a single component in its own window
+    #[arg(long, value_enum)]
+    widget: Option<StandaloneWidget>,
+    /// Open the compact mini-player instead of the full overlay
+    #[arg(long, conflicts_with = "widget")]
+    mini_player: bool,
+    /// Open a regular window instead of a layer-shell overlay
+    #[arg(long)]
+    window_mode: bool,
+    /// Print which backends are used and why others were skipped
+    #[arg(long, short)]
+    verbose: bool,
+    /// Check dependencies and configuration, then exit
+    #[arg(long)]
+    doctor: bool,
+    /// Print the player state as Waybar JSON lines whenever it changes
+    #[arg(long)]
+    status_stream: bool,
+    /// With --status-stream, print plain text lines from this template
+    /// instead, e.g. "{artist} - {title} ({elapsed}/{duration})"
+    #[arg(long, value_name = "TEMPLATE", requires = "status_stream")]
+    status_format: Option<String>,
+    #[command(subcommand)]
+    action: Option<RemoteAction>,
+}
+
+/// Actions sent to the running instance.
+#[derive(Clone, Copy, Subcommand)]
+enum RemoteAction {
+    /// Show the overlay if hidden, hide it otherwise
+    Toggle,
+    /// Show the overlay
+    Show,
+    /// Hide the overlay
+    Hide,
+}
+
+impl RemoteAction {
+    fn name(&self) -> &'static str {
+        match self {
+            RemoteAction::Toggle => "toggle",
+            RemoteAction::Show => "show",
+            RemoteAction::Hide => "hide",
         }
     }
 }
 
+const APP_ID: &str = "org.relm4.song_widget";
+
 fn main() {
-    let config: Config = match confy::load("yap", "yap.config") {
+    let cli = Cli::parse();
+    VERBOSE.store(cli.verbose, Ordering::Relaxed);
+
+    if let Some(action) = cli.action {
+        let app = gtk::Application::new(Some(APP_ID), Default::default());
+        if app.register(None::<&gtk::gio::Cancellable>).is_err() || !app.is_remote() {
+            println!("YAP-GUI is not running");
+            process::exit(1);
+        }
+        app.activate_action(action.name(), None);
+        // The action is sent asynchronously, so flush it before exiting.
+        if let Some(connection) = app.dbus_connection() {
+            if let Err(error) = connection.flush_sync(None::<&gtk::gio::Cancellable>) {
+                println!("Couldn't send {}: {}", action.name(), error);
+            }
+        }
+        return;
+    }
+
+    let loaded = match &cli.config {
+        Some(path) => confy::load_path(path),
+        None => confy::load("yap", "yap.config"),
+    };
+    let mut config: Config = match loaded {
         Ok(config) => config,
         Err(error) => {
             println!("{}", error);
             return;
         }
     };
-    let app = RelmApp::new("org.relm4.song_widget");
-    app.run::<YapModel>(config.general.miniature_directory.clone());
+    config.runtime.window_mode = cli.window_mode;
+    cleanup::init(&config.cleanup);
+
+    let server = match &cli.profile {
+        Some(profile) => match config.servers.iter().find(|server| &server.name == profile) {
+            Some(server) => Some(server),
+            None => {
+                println!("No server named {} in the config", profile);
+                process::exit(1);
+            }
+        },
+        None => config.servers.first(),
+    };
+    if let Some(server) = server {
+        if is_verbose() {
+            println!("Using MPD server {}", server.name);
+        }
+        mpd::select_server(server);
+    }
+
+    if cli.doctor {
+        let _ = gtk::init();
+        let passed = doctor::print_report(&doctor::run(&config));
+        process::exit(if passed { 0 } else { 1 });
+    }
+
+    if cli.status_stream {
+        status::stream(cli.status_format);
+        return;
+    }
+
+    for command in Yap::missing_flags() {
+        println!("The installed yap doesn't support `{}`, update it", command);
+    }
+
+    match cli.widget {
+        Some(widget) => {
+            let app = RelmApp::new(&format!("{}.{}", APP_ID, widget.name()));
+            app.run::<StandaloneModel>((config, widget));
+        }
+        None if cli.mini_player => {
+            let app = RelmApp::new(&format!("{}.mini", APP_ID));
+            app.run::<MiniPlayerModel>(config);
+        }
+        None => {
+            let app = RelmApp::new(APP_ID);
+            app.run::<YapModel>(config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(config: &Config) -> Config {
+        let path = std::env::temp_dir().join(format!("yap-config-{}.toml", std::process::id()));
+        confy::store_path(&path, config).unwrap();
+        let loaded = confy::load_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn default_config_can_be_stored() {
+        let loaded = round_trip(&Config::default());
+        assert!(loaded.servers.is_empty());
+        assert_eq!(loaded.web.address, default_web_address());
+    }
+
+    #[test]
+    fn servers_are_stored() {
+        let server = Server {
+            name: "Living room".to_string(),
+            host: "192.168.1.20".to_string(),
+            port: 6600,
+            socket: None,
+            password: Some("secret".to_string()),
+        };
+        let config = Config {
+            servers: vec![server.clone()],
+            ..Config::default()
+        };
+        assert_eq!(round_trip(&config).servers, vec![server]);
+    }
 }
+
 // let stdin = stdin();
 // let mut stdout = stdout().into_raw_mode().unwrap();
 //
@@ -0,0 +1,125 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use crate::thumbnails::miniature_path;
+use crate::yap_cli::yap_cli::Yap;
+
+/// How many times a miniature is fetched before giving up on it.
+const ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled for each one after it.
+const BACKOFF: Duration = if cfg!(test) {
+    Duration::from_millis(10)
+} else {
+    Duration::from_secs(2)
+};
+
+#[derive(Debug)]
+pub enum Progress {
+    Fetched(String, PathBuf),
+    Failed(String, String),
+}
+
+/// Fetches missing miniatures one at a time on a background thread, so a
+/// large library doesn't start dozens of downloads at once.
+pub struct Fetcher {
+    queue: mpsc::Sender<String>,
+}
+
+impl Fetcher {
+    /// `report` is called from the background thread once per queued song.
+    pub fn spawn(
+        command: String,
+        miniature_directory: PathBuf,
+        report: impl Fn(Progress) + Send + 'static,
+    ) -> Fetcher {
+        let (queue, songs) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            for song_name in songs {
+                report(fetch(&command, &miniature_directory, song_name));
+            }
+        });
+        Fetcher { queue }
+    }
+
+    pub fn queue(&self, song_name: String) {
+        let _ = self.queue.send(song_name);
+    }
+}
+
+fn fetch(command: &str, miniature_directory: &Path, song_name: String) -> Progress {
+    let mut error = String::new();
+    for attempt in 0..ATTEMPTS {
+        if attempt > 0 {
+            thread::sleep(BACKOFF * 2u32.pow(attempt - 1));
+        }
+        match Yap::fetch_miniature(command, &song_name) {
+            // yap decides the format, so look for whatever it wrote.
+            Ok(()) => match miniature_path(miniature_directory, &song_name) {
+                Some(path) => return Progress::Fetched(song_name, path),
+                None => error = "no miniature was written".to_string(),
+            },
+            Err(message) => error = message,
+        }
+    }
+    Progress::Failed(song_name, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use super::*;
+
+    /// Fetches with a stub `miniature_command` that writes a miniature for
+    /// "Found" and fails for anything else, counting its calls.
+    #[test]
+    fn reports_fetched_and_failed_miniatures() {
+        let directory = std::env::temp_dir().join(format!("yap-miniatures-{}", std::process::id()));
+        let miniatures = directory.join("miniatures");
+        fs::create_dir_all(&miniatures).unwrap();
+        let command = directory.join("stub");
+        fs::write(
+            &command,
+            format!(
+                "#!/bin/sh\necho \"$4\" >> '{calls}'\n\
+                 [ \"$4\" = Found ] || {{ echo 'no video' >&2; exit 1; }}\n\
+                 touch '{miniatures}/Found.jpg'\n",
+                calls = directory.join("calls").display(),
+                miniatures = miniatures.display(),
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&command, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let (sender, progress) = mpsc::channel();
+        let fetcher = Fetcher::spawn(
+            command.to_string_lossy().to_string(),
+            miniatures.clone(),
+            move |report| sender.send(report).unwrap(),
+        );
+        fetcher.queue("Found".to_string());
+        fetcher.queue("Missing".to_string());
+        let timeout = Duration::from_secs(10);
+        match progress.recv_timeout(timeout).unwrap() {
+            Progress::Fetched(song_name, path) => {
+                assert_eq!(song_name, "Found");
+                assert_eq!(path, miniatures.join("Found.jpg"));
+            }
+            other => panic!("expected Fetched, got {:?}", other),
+        }
+        match progress.recv_timeout(timeout).unwrap() {
+            Progress::Failed(song_name, error) => {
+                assert_eq!(song_name, "Missing");
+                assert_eq!(error, "no video");
+            }
+            other => panic!("expected Failed, got {:?}", other),
+        }
+        let calls = fs::read_to_string(directory.join("calls")).unwrap();
+        assert_eq!(calls, "Found\nMissing\nMissing\nMissing\n");
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+pub mod supervisor;
+
+use std::{
+    env,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    os::unix::net::UnixStream,
+    process::Command,
+    sync::RwLock,
+    time::Duration,
+};
+
+use crate::{expand_home, Server};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// Connections are also opened from the GTK thread, so an unreachable server
+/// must fail fast.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The server picked with `--profile` or in the power menu. `None` means the
+/// one named by the environment.
+static SELECTED: RwLock<Option<Address>> = RwLock::new(None);
+
+/// A server as `MPD_HOST` and `MPD_PORT` name it.
+#[derive(Clone, Debug, PartialEq)]
+struct Address {
+    host: String,
+    port: u16,
+}
+
+impl Address {
+    fn of(server: &Server) -> Address {
+        let host = server.socket.as_ref().unwrap_or(&server.host);
+        let host = match &server.password {
+            Some(password) => format!("{}@{}", password, host),
+            None => host.clone(),
+        };
+        Address {
+            host,
+            port: server.port,
+        }
+    }
+}
+
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+/// An audio output configured in MPD.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Output {
+    pub id: u32,
+    pub name: String,
+    pub plugin: String,
+    pub enabled: bool,
+}
+
+/// Minimal client for the MPD text protocol, used for the features `yap` does
+/// not expose.
+pub struct Mpd {
+    reader: BufReader<Box<dyn Stream>>,
+    pub version: String,
+    /// The selected server this connection was opened to, if any.
+    address: Option<Address>,
+}
+
+impl Mpd {
+    /// Connects to the selected server, or else to the one named by
+    /// `MPD_HOST` and `MPD_PORT`, defaulting to `localhost:6600`. `MPD_HOST`
+    /// may be a socket path and may carry a password as `password@host`, like
+    /// for `mpc`.
+    pub fn connect() -> Result<Mpd, String> {
+        let address = address();
+        let (password, host) = match address.host.rsplit_once('@') {
+            Some((password, host)) if !password.is_empty() => (Some(password), host),
+            _ => (None, address.host.as_str()),
+        };
+        let mut mpd = if host.starts_with('/') || host.starts_with('~') {
+            Mpd::connect_socket(host)?
+        } else {
+            Mpd::connect_tcp(host, address.port)?
+        };
+        mpd.address = Some(address.clone());
+        if let Some(password) = password {
+            mpd.command(&format!("password {}", quote(password)))?;
+        }
+        Ok(mpd)
+    }
+
+    pub fn connect_tcp(host: &str, port: u16) -> Result<Mpd, String> {
+        let error = |error: std::io::Error| {
+            format!("Couldn't connect to MPD at {}:{}: {}", host, port, error)
+        };
+        let mut last_error = None;
+        let mut connected = None;
+        for address in (host, port).to_socket_addrs().map_err(error)? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    connected = Some(stream);
+                    break;
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+        let stream = connected
+            .ok_or_else(|| error(last_error.unwrap_or_else(|| ErrorKind::NotFound.into())))?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)))
+            .map_err(|error| error.to_string())?;
+        Mpd::from_stream(Box::new(stream))
+    }
+
+    pub fn connect_socket(path: &str) -> Result<Mpd, String> {
+        let stream = UnixStream::connect(expand_home(path))
+            .map_err(|error| format!("Couldn't connect to MPD at {}: {}", path, error))?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)))
+            .map_err(|error| error.to_string())?;
+        Mpd::from_stream(Box::new(stream))
+    }
+
+    fn from_stream(stream: Box<dyn Stream>) -> Result<Mpd, String> {
+        let mut reader = BufReader::new(stream);
+        let mut greeting = String::new();
+        reader
+            .read_line(&mut greeting)
+            .map_err(|error| error.to_string())?;
+        match greeting.trim().strip_prefix("OK MPD ") {
+            Some(version) => Ok(Mpd {
+                version: version.to_string(),
+                reader,
+                address: None,
+            }),
+            None => Err(format!("Unexpected MPD greeting: {}", greeting.trim())),
+        }
+    }
+
+    /// Sends a command and returns the `key: value` pairs of the response.
+    pub fn command(&mut self, command: &str) -> Result<Vec<(String, String)>, String> {
+        self.reader
+            .get_mut()
+            .write_all(format!("{}\n", command).as_bytes())
+            .map_err(|error| error.to_string())?;
+        let mut pairs = vec![];
+        loop {
+            let mut line = String::new();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|error| error.to_string())?;
+            if read == 0 {
+                return Err("MPD closed the connection".to_string());
+            }
+            let line = line.trim_end_matches('\n');
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if let Some(error) = line.strip_prefix("ACK ") {
+                return Err(error.to_string());
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    /// Blocks until one of `subsystems` changes and returns the names of the
+    /// changed subsystems.
+    pub fn idle(&mut self, subsystems: &[&str]) -> Result<Vec<String>, String> {
+        self.reader
+            .get_mut()
+            .write_all(format!("idle {}\n", subsystems.join(" ")).as_bytes())
+            .map_err(|error| error.to_string())?;
+        let mut changed = vec![];
+        // Bytes read before a timeout stay in `buffer`, so a line that arrives
+        // across two reads isn't lost.
+        let mut buffer = vec![];
+        loop {
+            match self.reader.read_until(b'\n', &mut buffer) {
+                Ok(_) if !buffer.ends_with(b"\n") => {
+                    return Err("MPD closed the connection".to_string())
+                }
+                Ok(_) => {}
+                Err(error)
+                    if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    // Waiting on a server that is no longer selected would
+                    // miss every change of the new one.
+                    if self.address.as_ref().is_some_and(|own| *own != address()) {
+                        return Err("Another MPD server was selected".to_string());
+                    }
+                    continue;
+                }
+                Err(error) => return Err(error.to_string()),
+            }
+            let line = String::from_utf8_lossy(&buffer)
+                .trim_end_matches('\n')
+                .to_string();
+            buffer.clear();
+            if line == "OK" {
+                return Ok(changed);
+            }
+            if let Some(error) = line.strip_prefix("ACK ") {
+                return Err(error.to_string());
+            }
+            if let Some(subsystem) = line.strip_prefix("changed: ") {
+                changed.push(subsystem.to_string());
+            }
+        }
+    }
+
+    /// Returns the value of `key` in the response to `command`.
+    pub fn get(&mut self, command: &str, key: &str) -> Result<Option<String>, String> {
+        Ok(self
+            .command(command)?
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value))
+    }
+}
+
+impl Mpd {
+    pub fn outputs(&mut self) -> Result<Vec<Output>, String> {
+        let mut outputs: Vec<Output> = vec![];
+        for (key, value) in self.command("outputs")? {
+            match key.as_str() {
+                "outputid" => outputs.push(Output {
+                    id: value.parse().map_err(|_| "Invalid output id")?,
+                    name: String::new(),
+                    plugin: String::new(),
+                    enabled: false,
+                }),
+                "outputname" | "plugin" | "outputenabled" => {
+                    let output = outputs.last_mut().ok_or("Output without id")?;
+                    match key.as_str() {
+                        "outputname" => output.name = value,
+                        "plugin" => output.plugin = value,
+                        _ => output.enabled = value == "1",
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(outputs)
+    }
+
+    pub fn set_output_enabled(&mut self, id: u32, enabled: bool) -> Result<(), String> {
+        let command = if enabled {
+            "enableoutput"
+        } else {
+            "disableoutput"
+        };
+        self.command(&format!("{} {}", command, id)).map(|_| ())
+    }
+}
+
+fn address() -> Address {
+    if let Some(address) = SELECTED.read().unwrap().as_ref() {
+        return address.clone();
+    }
+    Address {
+        host: env::var("MPD_HOST").unwrap_or_else(|_| "localhost".to_string()),
+        port: env::var("MPD_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(6600),
+    }
+}
+
+/// Makes `server` the MPD instance used by the GUI. Connections opened before
+/// keep talking to the previous server; idle connections notice and fail.
+pub fn select_server(server: &Server) {
+    *SELECTED.write().unwrap() = Some(Address::of(server));
+}
+
+/// Whether the GUI talks to `server`, because it was selected or because the
+/// environment names it.
+pub fn is_selected(server: &Server) -> bool {
+    Address::of(server) == address()
+}
+
+/// Points the `MPD_HOST` and `MPD_PORT` of `command` at the selected server,
+/// so the `yap` it runs uses the same MPD as the GUI.
+pub fn use_selected_server(command: &mut Command) -> &mut Command {
+    if let Some(address) = SELECTED.read().unwrap().as_ref() {
+        command
+            .env("MPD_HOST", &address.host)
+            .env("MPD_PORT", address.port.to_string());
+    }
+    command
+}
+
+/// Quotes an argument so it can be passed to an MPD command.
+pub fn quote(argument: &str) -> String {
+    format!(
+        "\"{}\"",
+        argument.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::*;
+
+    /// Starts an MPD stand-in that greets with `version`, reports every
+    /// command it gets and answers all but `idle` with `OK`.
+    fn mock_server(version: &'static str) -> (u16, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (commands, received) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let commands = commands.clone();
+                thread::spawn(move || {
+                    writeln!(stream, "OK MPD {}", version).unwrap();
+                    let reader = BufReader::new(stream.try_clone().unwrap());
+                    for command in reader.lines() {
+                        let command = command.unwrap();
+                        if !command.starts_with("idle") {
+                            stream.write_all(b"OK\n").unwrap();
+                        }
+                        let _ = commands.send(command);
+                    }
+                });
+            }
+        });
+        (port, received)
+    }
+
+    fn server(port: u16, password: Option<&str>) -> Server {
+        Server {
+            name: port.to_string(),
+            host: "127.0.0.1".to_string(),
+            port,
+            socket: None,
+            password: password.map(str::to_string),
+        }
+    }
+
+    /// The selection is global, so switching is checked in a single test.
+    #[test]
+    fn follows_the_selected_server() {
+        let (first_port, first_commands) = mock_server("0.23.1");
+        let (second_port, _) = mock_server("0.23.2");
+
+        select_server(&server(first_port, Some("p@ss")));
+        assert_eq!(Mpd::connect().unwrap().version, "0.23.1");
+        assert_eq!(first_commands.recv().unwrap(), "password \"p@ss\"");
+        let (connected, ready) = mpsc::channel();
+        let idling = thread::spawn(move || {
+            let mut idle = Mpd::connect().unwrap();
+            connected.send(()).unwrap();
+            idle.idle(&["mixer"]).is_err()
+        });
+        ready.recv().unwrap();
+
+        select_server(&server(second_port, None));
+        assert_eq!(Mpd::connect().unwrap().version, "0.23.2");
+        assert!(idling.join().unwrap());
+
+        let output =
+            use_selected_server(Command::new("sh").args(["-c", "echo $MPD_HOST:$MPD_PORT"]))
+                .output()
+                .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            format!("127.0.0.1:{}", second_port)
+        );
+    }
+
+    #[test]
+    fn idle_keeps_a_line_split_by_a_read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"OK MPD 0.23.5\n").unwrap();
+            let mut command = String::new();
+            BufReader::new(stream.try_clone().unwrap())
+                .read_line(&mut command)
+                .unwrap();
+            assert_eq!(command, "idle mixer\n");
+            stream.write_all(b"changed: mix").unwrap();
+            thread::sleep(TIMEOUT + Duration::from_millis(500));
+            stream.write_all(b"er\nOK\n").unwrap();
+        });
+        let mut mpd = Mpd::connect_tcp("127.0.0.1", port).unwrap();
+        assert_eq!(mpd.idle(&["mixer"]).unwrap(), vec!["mixer".to_string()]);
+    }
+}
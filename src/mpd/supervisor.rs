@@ -0,0 +1,145 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crate::{expand_home, Supervisor};
+
+use super::Mpd;
+
+#[derive(Clone, Debug, Default)]
+pub struct Health {
+    pub version: Option<String>,
+    /// Seconds since the daemon was started.
+    pub uptime: Option<u64>,
+}
+
+impl Health {
+    pub fn is_up(&self) -> bool {
+        self.version.is_some()
+    }
+}
+
+pub fn health() -> Health {
+    match Mpd::connect() {
+        Ok(mut mpd) => Health {
+            uptime: mpd
+                .get("stats", "uptime")
+                .ok()
+                .flatten()
+                .and_then(|uptime| uptime.parse().ok()),
+            version: Some(mpd.version),
+        },
+        Err(_) => Health::default(),
+    }
+}
+
+/// Returns the pid of the running daemon, as recorded in its pid file.
+fn running_pid(pid_file: &Path) -> Option<u32> {
+    let pid: u32 = fs::read_to_string(pid_file).ok()?.trim().parse().ok()?;
+    Path::new("/proc")
+        .join(pid.to_string())
+        .exists()
+        .then_some(pid)
+}
+
+/// Whether an MPD instance is running, either according to its pid file or
+/// because its socket accepts connections.
+pub fn is_running(settings: &Supervisor) -> bool {
+    settings
+        .pid_file
+        .as_deref()
+        .and_then(|pid_file| running_pid(&expand_home(pid_file)))
+        .is_some()
+        || Mpd::connect().is_ok()
+}
+
+pub fn restart(settings: &Supervisor) -> Result<(), String> {
+    if settings.use_systemd {
+        return systemctl("restart");
+    }
+    if is_running(settings) {
+        stop(settings)?;
+    }
+    start(settings)
+}
+
+fn systemctl(action: &str) -> Result<(), String> {
+    let output = Command::new("systemctl")
+        .args(["--user", action, "mpd"])
+        .output()
+        .map_err(|error| format!("Failed to execute systemctl: {}", error))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+fn stop(settings: &Supervisor) -> Result<(), String> {
+    let output = Command::new("mpd")
+        .arg("--kill")
+        .output()
+        .map_err(|error| format!("Failed to execute mpd: {}", error))?;
+    if !output.status.success() {
+        // `mpd --kill` needs a pid_file in mpd.conf, fall back to our own.
+        let pid = settings
+            .pid_file
+            .as_deref()
+            .and_then(|pid_file| running_pid(&expand_home(pid_file)))
+            .ok_or_else(|| String::from_utf8_lossy(&output.stderr).trim().to_string())?;
+        Command::new("kill")
+            .arg(pid.to_string())
+            .output()
+            .map_err(|error| format!("Failed to stop MPD: {}", error))?;
+    }
+    for _ in 0..20 {
+        if !is_running(settings) {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    Err("MPD didn't stop".to_string())
+}
+
+/// Starts MPD with its stderr appended to the configured log file. On
+/// failure the error contains what MPD printed.
+fn start(settings: &Supervisor) -> Result<(), String> {
+    let log_path = expand_home(&settings.log_file);
+    if let Some(parent) = log_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .read(true)
+        .open(&log_path)
+        .map_err(|error| format!("Couldn't open {}: {}", log_path.display(), error))?;
+    let start = log.seek(SeekFrom::End(0)).unwrap_or(0);
+    let status = Command::new("mpd")
+        .stdout(Stdio::null())
+        .stderr(
+            log.try_clone()
+                .map_err(|error| format!("Couldn't open {}: {}", log_path.display(), error))?,
+        )
+        .status()
+        .map_err(|error| format!("Failed to execute mpd: {}", error))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(read_from(&mut log, start)
+            .filter(|output| !output.is_empty())
+            .unwrap_or_else(|| format!("mpd exited with {}", status)))
+    }
+}
+
+fn read_from(file: &mut File, offset: u64) -> Option<String> {
+    let mut output = String::new();
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_to_string(&mut output).ok()?;
+    Some(output.trim().to_string())
+}
@@ -0,0 +1,395 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use gtk::prelude::*;
+use relm4::Sender;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::status::player_state;
+use crate::widgets::yap_widget::YapEvents;
+use crate::yap_cli::yap_cli::{Song, Yap};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// How long a write may block before the client is given up on, so a client
+/// that stops reading can't stall notifications to the others.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+struct Request {
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: &str) -> RpcError {
+        RpcError {
+            code: INVALID_PARAMS,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// The sending half of a client connection. Responses and notifications
+/// come from different threads, so each line is written whole under the lock.
+type Writer = Arc<Mutex<UnixStream>>;
+type Subscribers = Arc<Mutex<Vec<Writer>>>;
+
+/// `$XDG_RUNTIME_DIR/yap-gui.sock`, where the running GUI listens for
+/// JSON-RPC requests.
+pub fn socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir().map(|dir| dir.join("yap-gui.sock"))
+}
+
+/// Listens on the remote-control socket in the background. UI requests are
+/// forwarded to the overlay through `sender`.
+pub fn serve(sender: Sender<YapEvents>) {
+    let Some(path) = socket_path() else {
+        println!("XDG_RUNTIME_DIR is not set, remote control is disabled");
+        return;
+    };
+    if UnixStream::connect(&path).is_err() {
+        let _ = fs::remove_file(&path);
+    }
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(error) => {
+            println!("Couldn't listen on {}: {}", path.display(), error);
+            return;
+        }
+    };
+    relm4::main_application().connect_shutdown(move |_| {
+        let _ = fs::remove_file(&path);
+    });
+    let subscribers: Subscribers = Arc::new(Mutex::new(vec![]));
+
+    let notified = subscribers.clone();
+    thread::spawn(move || notify_subscribers(notified));
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            let subscribers = subscribers.clone();
+            thread::spawn(move || handle_client(stream, sender, subscribers));
+        }
+    });
+}
+
+/// Writes `message` as one line. Returns `false` once the client is gone.
+fn send(writer: &Writer, message: &Value) -> bool {
+    let line = format!("{}\n", message);
+    writer.lock().unwrap().write_all(line.as_bytes()).is_ok()
+}
+
+/// Sends a `player` notification to every subscriber whenever the player
+/// state changes.
+fn notify_subscribers(subscribers: Subscribers) {
+    let mut last_state = None;
+    loop {
+        thread::sleep(Duration::from_millis(500));
+        if subscribers.lock().unwrap().is_empty() {
+            last_state = None;
+            continue;
+        }
+        let state = player_state();
+        if last_state.as_ref() == Some(&state) {
+            continue;
+        }
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "player",
+            "params": state,
+        });
+        broadcast(&subscribers, &notification);
+        last_state = Some(state);
+    }
+}
+
+/// Sends `message` to every subscriber and drops the ones that are gone or
+/// too slow. The list isn't locked while writing, so clients can still
+/// subscribe meanwhile.
+fn broadcast(subscribers: &Subscribers, message: &Value) {
+    let writers = subscribers.lock().unwrap().clone();
+    let failed: Vec<Writer> = writers
+        .into_iter()
+        .filter(|writer| !send(writer, message))
+        .collect();
+    if !failed.is_empty() {
+        subscribers
+            .lock()
+            .unwrap()
+            .retain(|writer| !failed.iter().any(|failed| Arc::ptr_eq(failed, writer)));
+    }
+}
+
+fn handle_client(stream: UnixStream, sender: Sender<YapEvents>, subscribers: Subscribers) {
+    if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+        return;
+    }
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let writer: Writer = Arc::new(Mutex::new(writer));
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, result) = match serde_json::from_str::<Value>(&line) {
+            Ok(value) => match serde_json::from_value::<Request>(value) {
+                Ok(request) => {
+                    let result = if request.method == "subscribe" {
+                        subscribers.lock().unwrap().push(writer.clone());
+                        Ok(Value::Bool(true))
+                    } else {
+                        call(&request.method, &request.params, &sender)
+                    };
+                    // Requests without an id are notifications and get no
+                    // response, not even on errors.
+                    let Some(id) = request.id else {
+                        continue;
+                    };
+                    (Some(id), result)
+                }
+                Err(error) => (
+                    None,
+                    Err(RpcError {
+                        code: INVALID_REQUEST,
+                        message: error.to_string(),
+                    }),
+                ),
+            },
+            Err(error) => (
+                None,
+                Err(RpcError {
+                    code: PARSE_ERROR,
+                    message: error.to_string(),
+                }),
+            ),
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": error.code, "message": error.message },
+            }),
+        };
+        if !send(&writer, &response) {
+            return;
+        }
+    }
+}
+
+fn internal_error(message: String) -> RpcError {
+    RpcError {
+        code: INTERNAL_ERROR,
+        message,
+    }
+}
+
+fn string_param(params: &Value, name: &str) -> Result<String, RpcError> {
+    params[name]
+        .as_str()
+        .map(|value| value.to_string())
+        .ok_or_else(|| RpcError::invalid_params(&format!("Missing string parameter {}", name)))
+}
+
+fn number_param(params: &Value, name: &str) -> Result<u64, RpcError> {
+    params[name]
+        .as_u64()
+        .ok_or_else(|| RpcError::invalid_params(&format!("Missing number parameter {}", name)))
+}
+
+fn position_param(params: &Value, name: &str) -> Result<u32, RpcError> {
+    u32::try_from(number_param(params, name)?)
+        .map_err(|_| RpcError::invalid_params(&format!("Parameter {} is too large", name)))
+}
+
+/// Asks the volume widget, which owns the sound server connection and knows
+/// the volume target in use.
+fn volume(sender: &Sender<YapEvents>, volume: Option<f64>) -> Result<f64, RpcError> {
+    let (reply, answer) = mpsc::channel();
+    sender.emit(YapEvents::Volume(volume, reply));
+    answer
+        .recv_timeout(Duration::from_secs(5))
+        .map_err(|_| internal_error("The volume widget didn't answer".to_string()))?
+        .map_err(internal_error)
+}
+
+fn ui(sender: &Sender<YapEvents>, event: YapEvents) -> Result<Value, RpcError> {
+    sender.emit(event);
+    Ok(Value::Bool(true))
+}
+
+/// Runs a single JSON-RPC method. Shared by the socket and the web remote.
+pub fn call(method: &str, params: &Value, sender: &Sender<YapEvents>) -> Result<Value, RpcError> {
+    match method {
+        "player.state" => Ok(json!(player_state())),
+        "player.play" => {
+            Yap::play();
+            Ok(Value::Bool(true))
+        }
+        "player.pause" => {
+            if Yap::status().is_ok_and(|status| !status.is_paused) {
+                Yap::toggle_pause();
+            }
+            Ok(Value::Bool(true))
+        }
+        "player.toggle" => {
+            match Yap::status() {
+                Ok(status) if status.is_paused => Yap::play(),
+                _ => Yap::toggle_pause(),
+            }
+            Ok(Value::Bool(true))
+        }
+        "player.next" => {
+            Yap::next();
+            Ok(Value::Bool(true))
+        }
+        "player.previous" => {
+            Yap::prev();
+            Ok(Value::Bool(true))
+        }
+        "player.seek" => {
+            Yap::seek(number_param(params, "percentage")?.min(100));
+            Ok(Value::Bool(true))
+        }
+        "volume.get" => volume(sender, None).map(|volume| json!(volume)),
+        "volume.set" => volume(
+            sender,
+            Some(number_param(params, "volume")?.min(100) as f64),
+        )
+        .map(|_| Value::Bool(true)),
+        "library.songs" => {
+            let query = params["query"].as_str().unwrap_or_default().to_lowercase();
+            let songs: Vec<Song> = Yap::get_songs()
+                .into_iter()
+                .filter(|song| {
+                    song.name.to_lowercase().contains(&query)
+                        || song.artist.to_lowercase().contains(&query)
+                })
+                .collect();
+            Ok(json!(songs))
+        }
+        "queue.list" => Ok(json!(Yap::get_queue())),
+        "queue.add" => {
+            Yap::add_to_queue(&string_param(params, "name")?);
+            Ok(Value::Bool(true))
+        }
+        "queue.play_next" => {
+            Yap::play_next(&string_param(params, "name")?);
+            Ok(Value::Bool(true))
+        }
+        "queue.remove" => {
+            Yap::remove_from_queue(position_param(params, "position")?);
+            Ok(Value::Bool(true))
+        }
+        "queue.move" => {
+            Yap::move_in_queue(
+                position_param(params, "from")?,
+                position_param(params, "to")?,
+            );
+            Ok(Value::Bool(true))
+        }
+        "queue.play" => {
+            Yap::play_at(position_param(params, "position")?);
+            Ok(Value::Bool(true))
+        }
+        "queue.clear" => {
+            Yap::clear_queue();
+            Ok(Value::Bool(true))
+        }
+        "queue.shuffle" => {
+            Yap::shuffle_queue();
+            Ok(Value::Bool(true))
+        }
+        "window.show" => ui(sender, YapEvents::Show),
+        "window.hide" => ui(sender, YapEvents::Hide),
+        "window.toggle" => ui(sender, YapEvents::ToggleVisible),
+        "ui.navigate" => ui(sender, YapEvents::Navigate(string_param(params, "page")?)),
+        "library.search" => ui(sender, YapEvents::Search(string_param(params, "query")?)),
+        _ => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Unknown method {}", method),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_requests_but_not_notifications() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (sender, _receiver) = relm4::channel();
+        thread::spawn(move || handle_client(server, sender, Arc::new(Mutex::new(vec![]))));
+        // Positions past u32::MAX are rejected before yap is run.
+        let mut writer = client.try_clone().unwrap();
+        writeln!(
+            writer,
+            r#"{{"jsonrpc":"2.0","method":"queue.play","params":{{"position":4294967296}}}}"#
+        )
+        .unwrap();
+        writeln!(
+            writer,
+            r#"{{"jsonrpc":"2.0","id":7,"method":"queue.play","params":{{"position":4294967296}}}}"#
+        )
+        .unwrap();
+        let mut line = String::new();
+        BufReader::new(client).read_line(&mut line).unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn drops_subscribers_that_stop_reading() {
+        let (_stalled, stalled_server) = UnixStream::pair().unwrap();
+        let (reader, reader_server) = UnixStream::pair().unwrap();
+        let subscribers: Subscribers = Arc::new(Mutex::new(vec![]));
+        for stream in [stalled_server, reader_server] {
+            stream.set_write_timeout(Some(WRITE_TIMEOUT)).unwrap();
+            subscribers
+                .lock()
+                .unwrap()
+                .push(Arc::new(Mutex::new(stream)));
+        }
+        let reading = subscribers.lock().unwrap()[1].clone();
+        let received = thread::spawn(move || BufReader::new(reader).lines().count());
+        // Big enough to fill the socket buffer of the client that never reads.
+        let notification = json!({ "method": "player", "params": "x".repeat(64 * 1024) });
+        let mut sent = 0;
+        while subscribers.lock().unwrap().len() == 2 && sent < 100 {
+            broadcast(&subscribers, &notification);
+            sent += 1;
+        }
+        let remaining = subscribers.lock().unwrap().clone();
+        assert_eq!(remaining.len(), 1);
+        assert!(Arc::ptr_eq(&remaining[0], &reading));
+        drop((subscribers, remaining, reading));
+        assert_eq!(received.join().unwrap(), sent);
+    }
+}
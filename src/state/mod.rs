@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::yap_cli::yap_cli::{QueueEntry, Song, Yap};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct QueueState {
+    // TOML needs plain values before the `songs` tables.
+    pub position: Option<u32>,
+    pub songs: Vec<Song>,
+}
+
+fn state_path(file: &str) -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("yap").join(file))
+}
+
+impl QueueState {
+    fn path() -> Option<PathBuf> {
+        state_path("queue.toml")
+    }
+
+    pub fn load() -> QueueState {
+        Self::path()
+            .and_then(|path| confy::load_path(path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = Self::path() {
+            if let Err(error) = confy::store_path(path, self) {
+                println!("Error while saving queue state: {}", error);
+            }
+        }
+    }
+
+    pub fn clear() {
+        QueueState::default().save();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.songs.is_empty()
+    }
+
+    /// Adds the saved songs back to the MPD queue and moves playback to the
+    /// saved position, leaving it paused.
+    pub fn restore(&self) {
+        for song in &self.songs {
+            Yap::add_to_queue(&song.name);
+        }
+        if let Some(position) = self.position {
+            Yap::play_at(position);
+            Yap::toggle_pause();
+        }
+    }
+}
+
+impl QueueEntry {
+    /// Adds the song back to the queue at the position it was removed from.
+    pub fn restore(&self) {
+        Yap::add_to_queue(&self.song.name);
+        if let Some(last) = Yap::get_queue().last() {
+            if last.position != self.position {
+                Yap::move_in_queue(last.position, self.position);
+            }
+        }
+    }
+}
+
+/// Screen corner the mini-player is anchored to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Corner {
+    pub fn next(self) -> Corner {
+        match self {
+            Corner::TopLeft => Corner::TopRight,
+            Corner::TopRight => Corner::BottomRight,
+            Corner::BottomRight => Corner::BottomLeft,
+            Corner::BottomLeft => Corner::TopLeft,
+        }
+    }
+
+    pub fn is_top(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::TopRight)
+    }
+
+    pub fn is_left(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::BottomLeft)
+    }
+}
+
+/// Where the mini-player was last shown and how big it was.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MiniPlayerState {
+    pub corner: Corner,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Default for MiniPlayerState {
+    fn default() -> Self {
+        Self {
+            corner: Corner::default(),
+            width: 420,
+            height: 110,
+        }
+    }
+}
+
+impl MiniPlayerState {
+    pub fn load() -> MiniPlayerState {
+        state_path("mini_player.toml")
+            .and_then(|path| confy::load_path(path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = state_path("mini_player.toml") {
+            if let Err(error) = confy::store_path(path, self) {
+                println!("Error while saving mini-player state: {}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_state_round_trips_while_playing() {
+        let path = std::env::temp_dir().join(format!("yap-queue-{}.toml", std::process::id()));
+        let state = QueueState {
+            position: Some(1),
+            songs: vec![
+                Song {
+                    name: "First".to_string(),
+                    artist: "Band".to_string(),
+                },
+                Song {
+                    name: "Second".to_string(),
+                    artist: "Band".to_string(),
+                },
+            ],
+        };
+        confy::store_path(&path, &state).unwrap();
+        let loaded: QueueState = confy::load_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.position, Some(1));
+        assert_eq!(loaded.songs, state.songs);
+    }
+}
@@ -0,0 +1,184 @@
+use std::{thread, time::Duration};
+
+use serde::Serialize;
+
+use crate::cleanup;
+use crate::yap_cli::yap_cli::{Song, Status, Time, Yap};
+
+const DEFAULT_TEMPLATE: &str = "{artist} - {title}";
+const TOOLTIP_TEMPLATE: &str = "{title}\n{artist}\n{elapsed} / {duration}";
+
+/// One line of a Waybar custom module with `"return-type": "json"`.
+#[derive(PartialEq, Serialize)]
+struct WaybarOutput {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+    percentage: u8,
+}
+
+/// What is playing, as reported to remote-control clients.
+#[derive(Clone, PartialEq, Serialize)]
+pub struct PlayerState {
+    pub state: &'static str,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Elapsed and total time in seconds.
+    pub elapsed: u32,
+    pub duration: u32,
+    pub percentage: u8,
+    pub random: bool,
+    pub repeat: bool,
+    /// Queue position of the current song.
+    pub position: Option<u32>,
+}
+
+pub fn player_state() -> PlayerState {
+    let current = Yap::current().map(|(song, time)| (cleanup::clean(&song), time));
+    let status = Yap::status().ok();
+    PlayerState {
+        state: state(&current, &status),
+        title: current.as_ref().map(|(song, _)| song.name.clone()),
+        artist: current.as_ref().map(|(song, _)| song.artist.clone()),
+        elapsed: current
+            .as_ref()
+            .map_or(0, |(_, time)| time.min * 60 + time.sec),
+        duration: current
+            .as_ref()
+            .map_or(0, |(_, time)| time.tot_min * 60 + time.tot_sec),
+        percentage: current.as_ref().map_or(0, |(_, time)| time.perc),
+        random: status.as_ref().is_some_and(|status| status.random),
+        repeat: status.as_ref().is_some_and(|status| status.repeat),
+        position: status.as_ref().and_then(|status| status.position),
+    }
+}
+
+/// Prints the player state every time it changes, forever. Without a
+/// template each line is Waybar JSON, with one it is plain text for Polybar
+/// or i3blocks.
+pub fn stream(template: Option<String>) {
+    let mut last_line = None;
+    loop {
+        let line = render(template.as_deref());
+        if last_line.as_ref() != Some(&line) {
+            println!("{}", line);
+            last_line = Some(line);
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+fn render(template: Option<&str>) -> String {
+    let current = Yap::current().map(|(song, time)| (cleanup::clean(&song), time));
+    let status = Yap::status().ok();
+    match template {
+        Some(template) => fill(template, &current, &status),
+        None => {
+            let output = WaybarOutput {
+                text: fill(DEFAULT_TEMPLATE, &current, &status),
+                tooltip: fill(TOOLTIP_TEMPLATE, &current, &status),
+                class: state(&current, &status),
+                percentage: current.as_ref().map_or(0, |(_, time)| time.perc),
+            };
+            serde_json::to_string(&output).unwrap_or_default()
+        }
+    }
+}
+
+fn state(current: &Option<(Song, Time)>, status: &Option<Status>) -> &'static str {
+    match (current, status) {
+        (None, _) => "stopped",
+        (Some(_), Some(status)) if status.is_paused => "paused",
+        (Some(_), _) => "playing",
+    }
+}
+
+/// Replaces `{title}`, `{artist}`, `{elapsed}`, `{duration}`, `{percentage}`
+/// and `{state}` in `template`. Nothing is printed while stopped.
+fn fill(template: &str, current: &Option<(Song, Time)>, status: &Option<Status>) -> String {
+    let Some((song, time)) = current else {
+        return String::new();
+    };
+    // Substituted values are copied as they are, so a title containing
+    // `{artist}` isn't filled in again.
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let value = match &rest[1..end] {
+            "title" => song.name.clone(),
+            "artist" => song.artist.clone(),
+            "elapsed" => format!("{}:{:02}", time.min, time.sec),
+            "duration" => format!("{}:{:02}", time.tot_min, time.tot_sec),
+            "percentage" => time.perc.to_string(),
+            "state" => state(current, status).to_string(),
+            _ => {
+                filled.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        filled.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing(name: &str, artist: &str) -> Option<(Song, Time)> {
+        let song = Song {
+            name: name.to_string(),
+            artist: artist.to_string(),
+        };
+        let time = Time {
+            min: 1,
+            sec: 5,
+            tot_min: 3,
+            tot_sec: 30,
+            perc: 31,
+        };
+        Some((song, time))
+    }
+
+    #[test]
+    fn fills_every_placeholder() {
+        let template = "{state}: {artist} - {title} {elapsed}/{duration} ({percentage}%)";
+        assert_eq!(
+            fill(template, &playing("Song", "Band"), &None),
+            "playing: Band - Song 1:05/3:30 (31%)"
+        );
+    }
+
+    #[test]
+    fn keeps_placeholders_inside_values() {
+        assert_eq!(
+            fill(
+                "{title} by {artist}",
+                &playing("{artist} {x", "Band"),
+                &None
+            ),
+            "{artist} {x by Band"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_and_unclosed_braces() {
+        assert_eq!(
+            fill("{nope} {title} {", &playing("Song", "Band"), &None),
+            "{nope} Song {"
+        );
+    }
+
+    #[test]
+    fn prints_nothing_while_stopped() {
+        assert_eq!(fill("{title}", &None, &None), "");
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use gtk::gdk_pixbuf::{InterpType, Pixbuf};
+
+use crate::cover::fnv1a;
+
+/// Formats miniatures may be saved in, in order of preference. WebP needs
+/// the webp-pixbuf-loader to be installed.
+pub const EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// Returns the miniature saved for `song_name`, in whichever of the
+/// `EXTENSIONS` it was downloaded as.
+pub fn miniature_path(miniature_directory: &Path, song_name: &str) -> Option<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|extension| miniature_directory.join(format!("{}.{}", song_name, extension)))
+        .find(|path| path.is_file())
+}
+
+/// Channel value under which a pixel counts as part of a black bar.
+const DARK: u8 = 24;
+
+/// Returns a square thumbnail of `source`, `size` pixels wide, with the black
+/// bars of 16:9 YouTube thumbnails cropped away. Thumbnails are cached in
+/// `$XDG_CACHE_HOME/yap/thumbnails` and regenerated when the source changes.
+/// Generating one reads and scales the whole image, so call this off the main
+/// thread.
+pub fn thumbnail(source: &Path, size: i32) -> Option<PathBuf> {
+    let cached = cache_path(source, size)?;
+    if is_fresh(&cached, source) {
+        return Some(cached);
+    }
+    let pixbuf = match Pixbuf::from_file(source) {
+        Ok(pixbuf) => pixbuf,
+        Err(error) => {
+            println!("Couldn't load {}: {}", source.display(), error);
+            return None;
+        }
+    };
+    let (x, y, width, height) = content_bounds(&pixbuf);
+    let side = width.min(height);
+    let square = pixbuf.new_subpixbuf(x + (width - side) / 2, y + (height - side) / 2, side, side);
+    let scaled = square.scale_simple(size, size, InterpType::Bilinear)?;
+    if let Some(directory) = cached.parent() {
+        let _ = fs::create_dir_all(directory);
+    }
+    match scaled.savev(&cached, "png", &[]) {
+        Ok(()) => Some(cached),
+        Err(error) => {
+            println!("Couldn't save {}: {}", cached.display(), error);
+            None
+        }
+    }
+}
+
+/// Thumbnails are kept apart by the kind of art they are made from and named
+/// after a hash of the whole source path, as song names often contain dots
+/// and a miniature, a cover and a placeholder can share a stem.
+fn cache_path(source: &Path, size: i32) -> Option<PathBuf> {
+    let cache = dirs::cache_dir()?.join("yap");
+    let kind = ["covers", "placeholders"]
+        .into_iter()
+        .find(|kind| source.starts_with(cache.join(kind)))
+        .unwrap_or("miniatures");
+    Some(
+        cache
+            .join("thumbnails")
+            .join(kind)
+            .join(size.to_string())
+            .join(format!("{:016x}.png", fnv1a(&source.to_string_lossy()))),
+    )
+}
+
+fn is_fresh(cached: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(cached), modified(source)) {
+        (Ok(cached), Ok(source)) => cached >= source,
+        _ => false,
+    }
+}
+
+/// Finds the part of the image inside letterbox and pillarbox bars, as
+/// `(x, y, width, height)`.
+fn content_bounds(pixbuf: &Pixbuf) -> (i32, i32, i32, i32) {
+    let width = pixbuf.width();
+    let height = pixbuf.height();
+    let pixels = pixbuf.read_pixel_bytes();
+    let rowstride = pixbuf.rowstride() as usize;
+    let channels = pixbuf.n_channels() as usize;
+    let is_dark = |x: i32, y: i32| {
+        let offset = y as usize * rowstride + x as usize * channels;
+        pixels[offset..offset + 3].iter().all(|value| *value < DARK)
+    };
+    // A few bright pixels, like a channel logo in the bar, still count as a
+    // bar.
+    let row_is_dark =
+        |y: i32| (0..width).filter(|x| !is_dark(*x, y)).count() < (width / 50) as usize + 1;
+    let column_is_dark =
+        |x: i32| (0..height).filter(|y| !is_dark(x, *y)).count() < (height / 50) as usize + 1;
+
+    let top = (0..height / 3).find(|y| !row_is_dark(*y)).unwrap_or(0);
+    let bottom = (height * 2 / 3..height)
+        .rev()
+        .find(|y| !row_is_dark(*y))
+        .unwrap_or(height - 1);
+    let left = (0..width / 3).find(|x| !column_is_dark(*x)).unwrap_or(0);
+    let right = (width * 2 / 3..width)
+        .rev()
+        .find(|x| !column_is_dark(*x))
+        .unwrap_or(width - 1);
+    (left, top, right - left + 1, bottom - top + 1)
+}
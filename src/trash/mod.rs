@@ -0,0 +1,186 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::library::SongRecord;
+use crate::yap_cli::yap_cli::{Song, Yap};
+
+const TRASH_DIRECTORY: &str = ".trash";
+
+/// Songs deleted from the GUI. Their audio files are moved under
+/// `<music_directory>/.trash` and they are only removed from the yap database
+/// once they are purged.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Trash {
+    pub entries: Vec<TrashEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TrashEntry {
+    /// Paths relative to the music directory that the files were moved
+    /// from. They are kept at the same relative path inside the trash.
+    pub files: Vec<String>,
+    pub deleted_at: u64,
+    // Last, as TOML needs plain values before tables.
+    pub song: Song,
+}
+
+impl Trash {
+    fn directory(music_directory: &Path) -> PathBuf {
+        music_directory.join(TRASH_DIRECTORY)
+    }
+
+    fn path(music_directory: &Path) -> PathBuf {
+        Self::directory(music_directory).join("trash.toml")
+    }
+
+    pub fn load(music_directory: &Path) -> Trash {
+        confy::load_path(Self::path(music_directory)).unwrap_or_default()
+    }
+
+    fn save(&self, music_directory: &Path) {
+        if let Err(error) = confy::store_path(Self::path(music_directory), self) {
+            println!("Error while saving trash: {}", error);
+        }
+    }
+
+    pub fn contains(&self, song: &Song) -> bool {
+        self.entries.iter().any(|entry| &entry.song == song)
+    }
+
+    /// Moves the audio file known to the library, along with every file
+    /// named after the song, into the trash.
+    pub fn trash_song(music_directory: &Path, record: &SongRecord) {
+        let trash_directory = Self::directory(music_directory);
+        if let Err(error) = fs::create_dir_all(&trash_directory) {
+            println!("Error while creating trash directory: {}", error);
+            return;
+        }
+        let mut paths = song_files(music_directory, &record.song.name);
+        if let Some(path) = &record.path {
+            let path = music_directory.join(path);
+            if path.is_file() && !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        let files = paths
+            .into_iter()
+            .filter_map(|path| {
+                let relative = path.strip_prefix(music_directory).ok()?.to_path_buf();
+                if let Err(error) = move_file(&path, &trash_directory.join(&relative)) {
+                    println!("Error while trashing {}: {}", path.display(), error);
+                    return None;
+                }
+                Some(relative.to_string_lossy().to_string())
+            })
+            .collect();
+        let mut trash = Self::load(music_directory);
+        trash.entries.push(TrashEntry {
+            song: record.song.clone(),
+            files,
+            deleted_at: now(),
+        });
+        trash.save(music_directory);
+    }
+
+    pub fn restore_song(music_directory: &Path, song: &Song) {
+        let trash_directory = Self::directory(music_directory);
+        let mut trash = Self::load(music_directory);
+        if let Some(index) = trash.entries.iter().position(|entry| &entry.song == song) {
+            let entry = trash.entries.remove(index);
+            for file in entry.files {
+                if let Err(error) =
+                    move_file(&trash_directory.join(&file), &music_directory.join(&file))
+                {
+                    println!("Error while restoring {}: {}", file, error);
+                }
+            }
+            trash.save(music_directory);
+        }
+    }
+
+    /// Permanently deletes every song that has been in the trash for longer
+    /// than `retention`.
+    pub fn purge(music_directory: &Path, retention: Duration) {
+        let trash_directory = Self::directory(music_directory);
+        let mut trash = Self::load(music_directory);
+        let now = now();
+        let (expired, kept): (Vec<TrashEntry>, Vec<TrashEntry>) = trash
+            .entries
+            .into_iter()
+            .partition(|entry| now.saturating_sub(entry.deleted_at) >= retention.as_secs());
+        if expired.is_empty() {
+            return;
+        }
+        for entry in expired {
+            Yap::delete_song(&entry.song.name);
+            for file in entry.files {
+                let _ = fs::remove_file(trash_directory.join(file));
+            }
+        }
+        trash.entries = kept;
+        trash.save(music_directory);
+    }
+}
+
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)
+}
+
+pub fn song_files(music_directory: &Path, song_name: &str) -> Vec<PathBuf> {
+    match fs::read_dir(music_directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy() == song_name)
+                    .unwrap_or(false)
+            })
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trash_round_trips() {
+        let music_directory =
+            std::env::temp_dir().join(format!("yap-trash-{}", std::process::id()));
+        let song = Song {
+            name: "Song".to_string(),
+            artist: "Band".to_string(),
+        };
+        let trash = Trash {
+            entries: vec![TrashEntry {
+                files: vec!["Song.mp3".to_string(), "Song.info.json".to_string()],
+                deleted_at: 1_700_000_000,
+                song: song.clone(),
+            }],
+        };
+        trash.save(&music_directory);
+        let loaded = Trash::load(&music_directory);
+        fs::remove_dir_all(&music_directory).unwrap();
+        assert!(loaded.contains(&song));
+        assert_eq!(loaded.entries[0].files, trash.entries[0].files);
+        assert_eq!(loaded.entries[0].deleted_at, 1_700_000_000);
+    }
+}
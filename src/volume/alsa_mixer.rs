@@ -0,0 +1,67 @@
+use alsa::mixer::{Mixer, Selem, SelemChannelId, SelemId};
+
+use super::VolumeBackend;
+
+/// Controls the `Master` element of the default ALSA mixer.
+pub struct AlsaBackend {
+    mixer: Mixer,
+}
+
+impl AlsaBackend {
+    pub fn new() -> Result<AlsaBackend, String> {
+        let mixer = Mixer::new("default", false).map_err(|error| error.to_string())?;
+        let backend = AlsaBackend { mixer };
+        backend.master()?;
+        Ok(backend)
+    }
+
+    fn master(&self) -> Result<Selem, String> {
+        self.mixer
+            .find_selem(&SelemId::new("Master", 0))
+            .ok_or_else(|| "No Master mixer control".to_string())
+    }
+
+    fn refresh(&mut self) {
+        let _ = self.mixer.handle_events();
+    }
+}
+
+impl VolumeBackend for AlsaBackend {
+    fn name(&self) -> &'static str {
+        "ALSA"
+    }
+
+    fn volume(&mut self) -> Result<f64, String> {
+        self.refresh();
+        let master = self.master()?;
+        let (min, max) = master.get_playback_volume_range();
+        let volume = master
+            .get_playback_volume(SelemChannelId::mono())
+            .map_err(|error| error.to_string())?;
+        Ok((volume - min) as f64 / (max - min).max(1) as f64 * 100.)
+    }
+
+    fn set_volume(&mut self, volume: f64) -> Result<(), String> {
+        let master = self.master()?;
+        let (min, max) = master.get_playback_volume_range();
+        let volume = min + ((max - min) as f64 * volume.clamp(0., 100.) / 100.).round() as i64;
+        master
+            .set_playback_volume_all(volume)
+            .map_err(|error| error.to_string())
+    }
+
+    fn is_muted(&mut self) -> Result<bool, String> {
+        self.refresh();
+        let switch = self
+            .master()?
+            .get_playback_switch(SelemChannelId::mono())
+            .map_err(|error| error.to_string())?;
+        Ok(switch == 0)
+    }
+
+    fn set_muted(&mut self, muted: bool) -> Result<(), String> {
+        self.master()?
+            .set_playback_switch_all(if muted { 0 } else { 1 })
+            .map_err(|error| error.to_string())
+    }
+}
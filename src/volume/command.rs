@@ -0,0 +1,42 @@
+use std::process::Command;
+
+use super::VolumeBackend;
+
+/// Fallback backend that shells out to `pamixer`.
+pub struct CommandBackend {}
+
+fn pamixer(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("pamixer")
+        .args(args)
+        .output()
+        .map_err(|error| format!("Failed to execute pamixer: {}", error))?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl VolumeBackend for CommandBackend {
+    fn name(&self) -> &'static str {
+        "pamixer"
+    }
+
+    fn volume(&mut self) -> Result<f64, String> {
+        let output = pamixer(&["--get-volume"])?;
+        output
+            .parse()
+            .map_err(|_| format!("Invalid volume: {}", output))
+    }
+
+    fn set_volume(&mut self, volume: f64) -> Result<(), String> {
+        pamixer(&["--set-volume", &(volume.round() as u32).to_string()]).map(|_| ())
+    }
+
+    fn is_muted(&mut self) -> Result<bool, String> {
+        let output = pamixer(&["--get-mute"])?;
+        output
+            .parse()
+            .map_err(|_| format!("Invalid mute state: {}", output))
+    }
+
+    fn set_muted(&mut self, muted: bool) -> Result<(), String> {
+        pamixer(&[if muted { "--mute" } else { "--unmute" }]).map(|_| ())
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(feature = "alsa")]
+mod alsa_mixer;
+mod command;
+mod mpd;
+#[cfg(feature = "pulse")]
+mod pulse;
+
+pub use mpd::MpdBackend;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A backend used by several widgets, so the app keeps a single connection to
+/// the sound server.
+pub type SharedBackend = Rc<RefCell<Box<dyn VolumeBackend>>>;
+
+/// A device of the sound server that streams can play on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sink {
+    pub index: u32,
+    pub description: String,
+    /// Whether the application's stream is currently playing on this sink.
+    pub active: bool,
+}
+
+/// A sound system whose output volume can be read and changed. Volumes are
+/// percentages between 0 and 100.
+pub trait VolumeBackend {
+    fn name(&self) -> &'static str;
+    fn volume(&mut self) -> Result<f64, String>;
+    fn set_volume(&mut self, volume: f64) -> Result<(), String>;
+    fn is_muted(&mut self) -> Result<bool, String>;
+    fn set_muted(&mut self, muted: bool) -> Result<(), String>;
+
+    /// Calls `notify` whenever the volume or mute state changes, including
+    /// changes made by other applications. Returns `false` if the backend
+    /// can't report changes and has to be polled instead.
+    fn subscribe(&mut self, _notify: Box<dyn Fn() + Send>) -> bool {
+        false
+    }
+
+    /// Lists the sinks the stream named by any of `streams` can be moved to.
+    /// Backends without per-stream routing return no sinks.
+    fn sinks(&mut self, _streams: &[String]) -> Result<Vec<Sink>, String> {
+        Ok(vec![])
+    }
+
+    fn move_stream(&mut self, _streams: &[String], _sink: u32) -> Result<(), String> {
+        Err(format!("{} can't move streams between sinks", self.name()))
+    }
+}
+
+/// Returns the first backend that can reach a sound server, preferring the
+/// native PulseAudio/PipeWire connection, then ALSA, then `pamixer`.
+pub fn detect() -> Box<dyn VolumeBackend> {
+    #[cfg(feature = "pulse")]
+    match pulse::PulseBackend::new() {
+        Ok(backend) => return Box::new(backend),
+        Err(error) if crate::is_verbose() => {
+            println!("PulseAudio volume backend unavailable: {}", error)
+        }
+        Err(_) => {}
+    }
+    #[cfg(feature = "alsa")]
+    match alsa_mixer::AlsaBackend::new() {
+        Ok(backend) => return Box::new(backend),
+        Err(error) if crate::is_verbose() => {
+            println!("ALSA volume backend unavailable: {}", error)
+        }
+        Err(_) => {}
+    }
+    Box::new(command::CommandBackend {})
+}
@@ -0,0 +1,77 @@
+use std::{thread, time::Duration};
+
+use crate::mpd::Mpd;
+
+use super::VolumeBackend;
+
+/// Controls MPD's own software volume, which only affects MPD's stream.
+pub struct MpdBackend {
+    muted_volume: Option<f64>,
+}
+
+impl MpdBackend {
+    pub fn new() -> MpdBackend {
+        MpdBackend { muted_volume: None }
+    }
+}
+
+impl VolumeBackend for MpdBackend {
+    fn name(&self) -> &'static str {
+        "MPD"
+    }
+
+    fn volume(&mut self) -> Result<f64, String> {
+        let volume = Mpd::connect()?
+            .get("status", "volume")?
+            .ok_or_else(|| "MPD has no mixer".to_string())?;
+        volume
+            .parse()
+            .map_err(|_| format!("Invalid volume: {}", volume))
+    }
+
+    fn set_volume(&mut self, volume: f64) -> Result<(), String> {
+        Mpd::connect()?
+            .command(&format!("setvol {}", volume.clamp(0., 100.).round()))
+            .map(|_| ())
+    }
+
+    fn is_muted(&mut self) -> Result<bool, String> {
+        Ok(self.muted_volume.is_some())
+    }
+
+    /// MPD has no mute switch, so muting sets the volume to 0 and unmuting
+    /// restores the previous one.
+    fn set_muted(&mut self, muted: bool) -> Result<(), String> {
+        if muted && self.muted_volume.is_none() {
+            self.muted_volume = Some(self.volume()?);
+            self.set_volume(0.)
+        } else if !muted {
+            match self.muted_volume.take() {
+                Some(volume) => self.set_volume(volume),
+                None => Ok(()),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Waits for `mixer` idle events on a separate connection.
+    fn subscribe(&mut self, notify: Box<dyn Fn() + Send>) -> bool {
+        thread::spawn(move || {
+            // Grows while connections fail before any event, e.g. when MPD
+            // refuses `idle` for lack of a permission.
+            let mut delay = Duration::from_secs(1);
+            loop {
+                if let Ok(mut mpd) = Mpd::connect() {
+                    while mpd.idle(&["mixer"]).is_ok() {
+                        delay = Duration::from_secs(1);
+                        notify();
+                    }
+                }
+                thread::sleep(delay);
+                delay = (delay * 2).min(Duration::from_secs(30));
+            }
+        });
+        true
+    }
+}
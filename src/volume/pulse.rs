@@ -0,0 +1,240 @@
+use std::{cell::RefCell, rc::Rc};
+
+use libpulse_binding::context::{subscribe::InterestMaskSet, Context, FlagSet, State};
+use libpulse_binding::volume::Volume;
+use libpulse_glib_binding::Mainloop;
+use pulsectl::controllers::{
+    types::{ApplicationInfo, DeviceInfo},
+    AppControl, DeviceControl, SinkController,
+};
+
+use super::{Sink, VolumeBackend};
+
+/// Talks to PulseAudio (or PipeWire through `pipewire-pulse`) over the native
+/// protocol and controls the default sink.
+pub struct PulseBackend {
+    controller: SinkController,
+    subscription: Option<Subscription>,
+}
+
+/// A second connection, driven by the GLib main loop, that receives sink
+/// change events.
+struct Subscription {
+    _mainloop: Mainloop,
+    _context: Rc<RefCell<Context>>,
+}
+
+impl Subscription {
+    fn new(notify: Box<dyn Fn() + Send>) -> Result<Subscription, String> {
+        let mainloop = Mainloop::new(None).ok_or("Couldn't create PulseAudio main loop")?;
+        let context =
+            Context::new(&mainloop, "yap-gui").ok_or("Couldn't create PulseAudio context")?;
+        let context = Rc::new(RefCell::new(context));
+        let weak_context = Rc::downgrade(&context);
+        context
+            .borrow_mut()
+            .set_state_callback(Some(Box::new(move || {
+                if let Some(context) = weak_context.upgrade() {
+                    let mut context = context.borrow_mut();
+                    if context.get_state() == State::Ready {
+                        context.subscribe(InterestMaskSet::SINK | InterestMaskSet::SERVER, |_| {});
+                    }
+                }
+            })));
+        context
+            .borrow_mut()
+            .set_subscribe_callback(Some(Box::new(move |_, _, _| notify())));
+        context
+            .borrow_mut()
+            .connect(None, FlagSet::NOFLAGS, None)
+            .map_err(|error| error.to_string())?;
+        Ok(Subscription {
+            _mainloop: mainloop,
+            _context: context,
+        })
+    }
+}
+
+impl PulseBackend {
+    pub fn new() -> Result<PulseBackend, String> {
+        let mut controller = SinkController::create().map_err(|error| format!("{:?}", error))?;
+        controller
+            .get_default_device()
+            .map_err(|error| format!("{:?}", error))?;
+        Ok(PulseBackend {
+            controller,
+            subscription: None,
+        })
+    }
+
+    fn default_sink(&mut self) -> Result<DeviceInfo, String> {
+        self.controller
+            .get_default_device()
+            .map_err(|error| format!("{:?}", error))
+    }
+
+    /// Finds the playback stream whose media or application name is one of
+    /// `names`.
+    fn stream(&mut self, names: &[String]) -> Result<Option<ApplicationInfo>, String> {
+        let streams = self
+            .controller
+            .list_applications()
+            .map_err(|error| format!("{:?}", error))?;
+        let named = |name: Option<String>| name.is_some_and(|name| names.contains(&name));
+        Ok(streams.into_iter().find(|stream| {
+            named(stream.name.clone()) || named(stream.proplist.get_str("application.name"))
+        }))
+    }
+}
+
+impl VolumeBackend for PulseBackend {
+    fn name(&self) -> &'static str {
+        "PulseAudio"
+    }
+
+    fn volume(&mut self) -> Result<f64, String> {
+        let sink = self.default_sink()?;
+        Ok(sink.volume.avg().0 as f64 / Volume::NORMAL.0 as f64 * 100.)
+    }
+
+    fn set_volume(&mut self, volume: f64) -> Result<(), String> {
+        let sink = self.default_sink()?;
+        let mut volumes = sink.volume;
+        let channels = volumes.len();
+        volumes.set(
+            channels,
+            Volume((volume.clamp(0., 100.) / 100. * Volume::NORMAL.0 as f64) as u32),
+        );
+        self.controller.set_device_volume_by_index(sink.index, &volumes);
+        Ok(())
+    }
+
+    fn is_muted(&mut self) -> Result<bool, String> {
+        Ok(self.default_sink()?.mute)
+    }
+
+    fn set_muted(&mut self, muted: bool) -> Result<(), String> {
+        let sink = self.default_sink()?;
+        self.controller.set_device_mute_by_index(sink.index, muted);
+        Ok(())
+    }
+
+    fn sinks(&mut self, streams: &[String]) -> Result<Vec<Sink>, String> {
+        let current = self.stream(streams)?.map(|stream| stream.connection_id);
+        let devices = self
+            .controller
+            .list_devices()
+            .map_err(|error| format!("{:?}", error))?;
+        Ok(devices
+            .into_iter()
+            .map(|device| Sink {
+                index: device.index,
+                description: device
+                    .description
+                    .or(device.name)
+                    .unwrap_or_else(|| device.index.to_string()),
+                active: current == Some(device.index),
+            })
+            .collect())
+    }
+
+    fn move_stream(&mut self, streams: &[String], sink: u32) -> Result<(), String> {
+        let stream = self
+            .stream(streams)?
+            .ok_or_else(|| format!("{} isn't playing", streams.join(" or ")))?;
+        self.controller
+            .move_app_by_index(stream.index, sink)
+            .map_err(|error| format!("{:?}", error))
+            .map(|_| ())
+    }
+
+    fn subscribe(&mut self, notify: Box<dyn Fn() + Send>) -> bool {
+        match Subscription::new(notify) {
+            Ok(subscription) => {
+                self.subscription = Some(subscription);
+                true
+            }
+            Err(error) => {
+                if crate::is_verbose() {
+                    println!("Couldn't subscribe to PulseAudio events: {}", error);
+                }
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+    use std::{panic, thread};
+
+    use super::*;
+
+    fn pactl(args: &[&str]) -> String {
+        let output = Command::new("pactl")
+            .args(args)
+            .output()
+            .expect("pactl isn't installed");
+        assert!(output.status.success(), "pactl {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Runs `test` with a null sink as the default sink, then puts the
+    /// previous default back.
+    fn with_null_sink(test: impl FnOnce() + panic::UnwindSafe) {
+        let previous_default = pactl(&["get-default-sink"]);
+        let module = pactl(&["load-module", "module-null-sink", "sink_name=yap_test"]);
+        pactl(&["set-default-sink", "yap_test"]);
+        let result = panic::catch_unwind(test);
+        pactl(&["set-default-sink", &previous_default]);
+        pactl(&["unload-module", &module]);
+        if let Err(panic) = result {
+            panic::resume_unwind(panic);
+        }
+    }
+
+    /// Needs a running PulseAudio or pipewire-pulse server:
+    /// `cargo test -- --ignored null_sink`
+    #[test]
+    #[ignore]
+    fn sets_volume_and_mute_of_a_null_sink() {
+        with_null_sink(|| {
+            let mut backend = PulseBackend::new().unwrap();
+            backend.set_volume(40.).unwrap();
+            assert!((backend.volume().unwrap() - 40.).abs() < 1.);
+            backend.set_muted(true).unwrap();
+            assert!(backend.is_muted().unwrap());
+            backend.set_muted(false).unwrap();
+            assert!(!backend.is_muted().unwrap());
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn notifies_changes_made_by_other_clients_to_a_null_sink() {
+        with_null_sink(|| {
+            let mut backend = PulseBackend::new().unwrap();
+            let (notify, notified) = mpsc::channel();
+            assert!(backend.subscribe(Box::new(move || {
+                let _ = notify.send(());
+            })));
+            // The subscription is driven by the default GLib main context.
+            let context = glib::MainContext::default();
+            let deadline = Instant::now() + Duration::from_secs(5);
+            let mut volume = 20;
+            while notified.try_recv().is_err() {
+                assert!(Instant::now() < deadline, "no change was notified");
+                // Keep changing the volume until the subscription is ready.
+                pactl(&["set-sink-volume", "yap_test", &format!("{}%", volume)]);
+                volume += 1;
+                for _ in 0..20 {
+                    context.iteration(false);
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+        });
+    }
+}
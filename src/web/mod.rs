@@ -0,0 +1,212 @@
+use std::{io::Read, net::SocketAddr, thread};
+
+use relm4::Sender;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::remote;
+use crate::widgets::yap_widget::YapEvents;
+use crate::Web;
+
+const INDEX: &str = include_str!("index.html");
+
+#[derive(Deserialize)]
+struct Call {
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Serves the web remote on `settings.address` in the background. Requests
+/// to `/rpc` take the same methods as the remote-control socket.
+pub fn serve(settings: &Web, sender: Sender<YapEvents>) {
+    let server = match Server::http(&settings.address) {
+        Ok(server) => server,
+        Err(error) => {
+            println!(
+                "Couldn't start web remote on {}: {}",
+                settings.address, error
+            );
+            return;
+        }
+    };
+    let loopback = server
+        .server_addr()
+        .to_ip()
+        .is_some_and(|address| address.ip().is_loopback());
+    if settings.token.is_none() && !loopback {
+        println!(
+            "Not starting the web remote on {}: set web.token to serve beyond localhost",
+            settings.address
+        );
+        return;
+    }
+    let token = settings.token.clone();
+    thread::spawn(move || run(server, token, sender));
+}
+
+fn run(server: Server, token: Option<String>, sender: Sender<YapEvents>) {
+    let Some(address) = server.server_addr().to_ip() else {
+        return;
+    };
+    for request in server.incoming_requests() {
+        let sender = sender.clone();
+        let token = token.clone();
+        thread::spawn(move || handle(request, address, token.as_deref(), &sender));
+    }
+}
+
+fn handle(
+    mut request: Request,
+    address: SocketAddr,
+    token: Option<&str>,
+    sender: &Sender<YapEvents>,
+) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let response = match (request.method(), path) {
+        (Method::Get, "/") => Response::from_string(INDEX).with_header(content_type("text/html")),
+        (Method::Post, "/rpc") if token.is_none() && !same_origin(&request, address) => {
+            json_response(json!({ "error": "Cross-origin request" })).with_status_code(403)
+        }
+        (Method::Post, "/rpc") if !authorized(&request, query, token) => {
+            json_response(json!({ "error": "Invalid token" })).with_status_code(401)
+        }
+        (Method::Post, "/rpc") => {
+            let mut body = String::new();
+            let _ = request.as_reader().read_to_string(&mut body);
+            match serde_json::from_str::<Call>(&body) {
+                Ok(call) => match remote::call(&call.method, &call.params, sender) {
+                    Ok(result) => json_response(json!({ "result": result })),
+                    Err(error) => {
+                        json_response(json!({ "error": error.message })).with_status_code(400)
+                    }
+                },
+                Err(error) => {
+                    json_response(json!({ "error": error.to_string() })).with_status_code(400)
+                }
+            }
+        }
+        _ => Response::from_string("Not found").with_status_code(404),
+    };
+    let _ = request.respond(response);
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Without a token, only pages served from the remote itself may call it.
+/// Checking `Host` stops DNS rebinding and checking `Origin` stops other
+/// sites from posting to it.
+fn same_origin(request: &Request, address: SocketAddr) -> bool {
+    let hosts = [address.to_string(), format!("localhost:{}", address.port())];
+    let allowed = |host: &str| hosts.iter().any(|allowed| allowed == host);
+    let host_matches = header(request, "Host").is_some_and(allowed);
+    let origin_matches = match header(request, "Origin") {
+        Some(origin) => origin.strip_prefix("http://").is_some_and(allowed),
+        None => true,
+    };
+    host_matches && origin_matches
+}
+
+/// Accepts the token as `Authorization: Bearer <token>` or as a `token`
+/// query parameter.
+fn authorized(request: &Request, query: &str, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    let bearer = format!("Bearer {}", token);
+    header(request, "Authorization") == Some(bearer.as_str())
+        || query
+            .split('&')
+            .any(|pair| pair.strip_prefix("token=") == Some(token))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+fn json_response(value: Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(value.to_string()).with_header(content_type("application/json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    use super::*;
+
+    /// Starts the web remote on a free port and returns that port.
+    fn start(token: Option<&str>) -> u16 {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let (sender, receiver) = relm4::channel();
+        let token = token.map(str::to_string);
+        thread::spawn(move || {
+            // Keep the UI end of the channel open while the server runs.
+            let _receiver = receiver;
+            run(server, token, sender)
+        });
+        port
+    }
+
+    /// Posts a UI-only call, which needs no `yap`, and returns the status.
+    fn post(port: u16, path: &str, headers: &[&str]) -> u16 {
+        let body = r#"{"method":"window.show"}"#;
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut request = format!("POST {} HTTP/1.1\r\n", path);
+        for header in headers {
+            request.push_str(&format!("{}\r\n", header));
+        }
+        request.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ));
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.split(' ').nth(1).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn accepts_same_origin_calls_without_a_token() {
+        let port = start(None);
+        let host = format!("Host: 127.0.0.1:{}", port);
+        let origin = format!("Origin: http://localhost:{}", port);
+        assert_eq!(post(port, "/rpc", &[&host]), 200);
+        assert_eq!(post(port, "/rpc", &[&host, &origin]), 200);
+    }
+
+    #[test]
+    fn rejects_other_hosts_and_origins_without_a_token() {
+        let port = start(None);
+        let host = format!("Host: 127.0.0.1:{}", port);
+        let rebound = format!("Host: attacker.example:{}", port);
+        assert_eq!(post(port, "/rpc", &[&rebound]), 403);
+        assert_eq!(
+            post(port, "/rpc", &[&host, "Origin: http://attacker.example"]),
+            403
+        );
+    }
+
+    #[test]
+    fn requires_the_token_when_one_is_set() {
+        let port = start(Some("secret"));
+        let host = format!("Host: attacker.example:{}", port);
+        assert_eq!(post(port, "/rpc", &[&host]), 401);
+        assert_eq!(post(port, "/rpc?token=wrong", &[&host]), 401);
+        assert_eq!(post(port, "/rpc?token=secret", &[&host]), 200);
+        assert_eq!(
+            post(port, "/rpc", &[&host, "Authorization: Bearer secret"]),
+            200
+        );
+    }
+}
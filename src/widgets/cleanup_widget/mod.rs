@@ -0,0 +1,226 @@
+use std::path::PathBuf;
+
+use gtk::glib::clone;
+use gtk::prelude::*;
+use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
+
+use crate::cleanup;
+use crate::glyphs;
+use crate::library::{self, info::Sidecars};
+use crate::yap_cli::yap_cli::{Song, Yap};
+use crate::{Config, Database};
+
+/// A song whose clean name differs from the one in the library.
+struct Change {
+    song: Song,
+    cleaned: Song,
+    selected: bool,
+}
+
+pub struct CleanupModel {
+    database: Database,
+    music_directory: PathBuf,
+    miniature_directory: PathBuf,
+    changes: Vec<Change>,
+    /// Bumped whenever `changes` is recomputed, so that the list is only
+    /// rebuilt then and not when a row is (de)selected.
+    generation: u32,
+}
+
+#[derive(Debug)]
+pub enum CleanupEvents {
+    Preview,
+    Select(usize, bool),
+    Apply,
+}
+
+#[derive(Debug)]
+pub enum CleanupOutput {
+    /// The selected songs were renamed, so the library should be reloaded.
+    Applied,
+    Error(String),
+}
+
+pub struct CleanupWidgets {
+    list: gtk::ListBox,
+    generation: u32,
+}
+
+impl SimpleComponent for CleanupModel {
+    type Input = CleanupEvents;
+    type Output = CleanupOutput;
+    type Init = Config;
+    type Root = gtk::Box;
+    type Widgets = CleanupWidgets;
+
+    fn init_root() -> Self::Root {
+        gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .vexpand(true)
+            .build()
+    }
+
+    fn init(
+        init: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let title_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .build();
+        let title = gtk::Label::builder()
+            .label("Title clean-up")
+            .name("title")
+            .hexpand(true)
+            .halign(gtk::Align::Start)
+            .margin_top(10)
+            .margin_bottom(10)
+            .margin_start(10)
+            .build();
+        let preview = gtk::Button::with_label(glyphs::RELOAD);
+        preview.set_tooltip_text(Some("Preview again"));
+        preview.add_css_class("playlist-button");
+        preview.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(CleanupEvents::Preview);
+        }));
+        let apply = gtk::Button::with_label(glyphs::CHECK);
+        apply.set_tooltip_text(Some("Rename the selected songs in the library"));
+        apply.add_css_class("playlist-button");
+        apply.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(CleanupEvents::Apply);
+        }));
+        title_box.append(&title);
+        title_box.append(&preview);
+        title_box.append(&apply);
+        let scrolled_window = gtk::ScrolledWindow::builder().vexpand(true).build();
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        list.add_css_class("not-transparent");
+        scrolled_window.set_child(Some(&list));
+        root.append(&title_box);
+        root.append(&scrolled_window);
+
+        let model = CleanupModel {
+            changes: changes(&init.database),
+            music_directory: init.general.music_directory(),
+            miniature_directory: init.general.miniature_directory(),
+            database: init.database,
+            generation: 0,
+        };
+        let widgets = CleanupWidgets {
+            list,
+            generation: model.generation,
+        };
+        build_list(&widgets.list, &model.changes, &sender);
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            CleanupEvents::Preview => {
+                self.changes = changes(&self.database);
+                self.generation += 1;
+            }
+            CleanupEvents::Select(index, selected) => {
+                if let Some(change) = self.changes.get_mut(index) {
+                    change.selected = selected;
+                }
+            }
+            CleanupEvents::Apply => {
+                let mut sidecars = Sidecars::index(&self.music_directory);
+                for change in self.changes.iter().filter(|change| change.selected) {
+                    let renamed = Yap::edit_song(&change.song.name, &change.cleaned)
+                        .map_err(|error| format!("Couldn't rename {}: {}", change.song.name, error))
+                        .and_then(|()| {
+                            library::move_song_files(
+                                &change.song.name,
+                                &change.cleaned.name,
+                                &self.miniature_directory,
+                                &mut sidecars,
+                            )
+                        });
+                    if let Err(error) = renamed {
+                        sender.output(CleanupOutput::Error(error)).unwrap();
+                    }
+                }
+                self.changes = changes(&self.database);
+                self.generation += 1;
+                sender.output(CleanupOutput::Applied).unwrap();
+            }
+        }
+    }
+
+    fn update_view(&self, widgets: &mut Self::Widgets, sender: ComponentSender<Self>) {
+        if widgets.generation != self.generation {
+            build_list(&widgets.list, &self.changes, &sender);
+            widgets.generation = self.generation;
+        }
+    }
+}
+
+fn changes(database: &Database) -> Vec<Change> {
+    library::songs(database)
+        .into_iter()
+        .filter_map(|record| {
+            let cleaned = cleanup::clean(&record.song);
+            (cleaned != record.song).then_some(Change {
+                song: record.song,
+                cleaned,
+                selected: true,
+            })
+        })
+        .collect()
+}
+
+fn build_list(list: &gtk::ListBox, changes: &[Change], sender: &ComponentSender<CleanupModel>) {
+    while let Some(row) = list.row_at_index(0) {
+        list.remove(&row);
+    }
+    if changes.is_empty() {
+        let empty = gtk::Label::builder()
+            .label("All titles are clean")
+            .margin_top(20)
+            .margin_bottom(20)
+            .build();
+        empty.add_css_class("song-label");
+        list.append(&empty);
+    }
+    for (index, change) in changes.iter().enumerate() {
+        let row = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(20)
+            .build();
+        let selected = gtk::CheckButton::builder().active(change.selected).build();
+        selected.connect_toggled(clone!(@strong sender => move |button| {
+            sender.input(CleanupEvents::Select(index, button.is_active()));
+        }));
+        let text = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .hexpand(true)
+            .build();
+        let before = gtk::Label::builder()
+            .label(&format!("{} - {}", change.song.name, change.song.artist))
+            .halign(gtk::Align::Start)
+            .wrap(true)
+            .build();
+        before.add_css_class("cleanup-before");
+        let after = gtk::Label::builder()
+            .label(&format!(
+                "{} - {}",
+                change.cleaned.name, change.cleaned.artist
+            ))
+            .halign(gtk::Align::Start)
+            .wrap(true)
+            .build();
+        after.add_css_class("song-label");
+        text.append(&before);
+        text.append(&after);
+        row.append(&selected);
+        row.append(&text);
+        let list_box_row = gtk::ListBoxRow::builder().child(&row).build();
+        list_box_row.add_css_class("list-row");
+        list_box_row.add_css_class("song-list-row");
+        list.append(&list_box_row);
+    }
+}
@@ -1,7 +1,12 @@
 use gtk::glib::signal::Propagation;
 use std::time::Duration;
 
+use crate::cleanup;
+use crate::glyphs;
+use crate::library::info::{Chapter, Sidecars};
+use crate::mpd::Mpd;
 use crate::yap_cli::yap_cli::Yap;
+use crate::Config;
 
 use glib::ControlFlow;
 use gtk::glib;
@@ -10,11 +15,25 @@ use gtk::prelude::*;
 use relm4::RelmWidgetExt;
 use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
 
+/// How the transport controls are laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ControlLayout {
+    /// Song info beside the progress bar and every button.
+    Full,
+    /// Song info above the play and skip buttons, for the mini-player.
+    Compact,
+}
+
 pub struct ControlModel {
+    layout: ControlLayout,
     pub is_paused: bool,
     pub is_repeating: bool,
     pub is_random: bool,
     pub current_song: String,
+    sidecars: Sidecars,
+    chapters: Vec<Chapter>,
+    elapsed: u32,
+    duration: u32,
 }
 
 #[derive(Debug)]
@@ -25,18 +44,32 @@ pub enum ControlEvents {
     Prev,
     Rand,
     Repeat,
+    Progress(String, u32, u32),
+    NextChapter,
+    PrevChapter,
+}
+
+#[derive(Debug)]
+pub enum ControlOutput {
+    /// Another song started playing. The name is empty when nothing plays.
+    SongChanged(String),
 }
 
 pub struct ControlWidgets {
     play_button: gtk::Button,
     repeat_button: gtk::Button,
     random_button: gtk::Button,
+    progress_bar: gtk::Scale,
+    prev_chapter_button: gtk::Button,
+    next_chapter_button: gtk::Button,
+    /// Song whose chapters are currently marked on the progress bar.
+    marked_song: String,
 }
 
 impl SimpleComponent for ControlModel {
     type Input = ControlEvents;
-    type Output = ();
-    type Init = ();
+    type Output = ControlOutput;
+    type Init = (Config, ControlLayout);
     type Root = gtk::Box;
     type Widgets = ControlWidgets;
 
@@ -48,11 +81,12 @@ impl SimpleComponent for ControlModel {
     }
 
     fn init(
-        _: Self::Init,
+        (config, layout): Self::Init,
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let current_status = Yap::status().expect("Error while retrieving YAP status");
+        let compact = layout == ControlLayout::Compact;
         let hbox = gtk::Box::builder()
             .orientation(gtk::Orientation::Horizontal)
             .vexpand(true)
@@ -88,6 +122,25 @@ impl SimpleComponent for ControlModel {
             .margin_end(20)
             .margin_bottom(10)
             .build();
+        if compact {
+            hbox.set_orientation(gtk::Orientation::Vertical);
+            hbox.remove_css_class("not-transparent");
+            song_info.set_margin_start(0);
+            for label in [&song_name_label, &song_artist_label] {
+                label.set_widget_name("");
+                label.set_halign(gtk::Align::Start);
+                label.set_vexpand(false);
+                label.set_margin_bottom(0);
+                label.set_width_chars(-1);
+                label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            }
+            song_name_label.set_label("");
+            song_name_label.add_css_class("mini-song-name");
+            control_box.set_height_request(-1);
+            control_box.set_margin_start(0);
+            control_box.set_margin_end(0);
+            control_box.set_margin_bottom(0);
+        }
         let progress_bar = gtk::Scale::builder()
             .orientation(gtk::Orientation::Horizontal)
             .adjustment(&gtk::Adjustment::new(0., 0., 100., 1., 1., 1.))
@@ -95,26 +148,43 @@ impl SimpleComponent for ControlModel {
             .vexpand(true)
             .build();
         let controls = gtk::Box::builder().hexpand(true).build();
-        let rand = gtk::Button::with_label("󰒟");
-        let prev = gtk::Button::with_label("󰒮");
+        let rand = gtk::Button::with_label(glyphs::SHUFFLE);
+        let prev_chapter = gtk::Button::with_label(glyphs::PREVIOUS_CHAPTER);
+        let prev = gtk::Button::with_label(glyphs::PREVIOUS);
         let toggle_play = gtk::Button::with_label(if current_status.is_paused {
-            ""
+            glyphs::PLAY
         } else {
-            "󰏤"
+            glyphs::PAUSE
         });
-        let next = gtk::Button::with_label("󰒭");
-        let repeat = gtk::Button::with_label("󰑖");
+        let next = gtk::Button::with_label(glyphs::NEXT);
+        let next_chapter = gtk::Button::with_label(glyphs::NEXT_CHAPTER);
+        let repeat = gtk::Button::with_label(glyphs::REPEAT);
         rand.set_class_active("active", current_status.random);
         repeat.set_class_active("active", current_status.repeat);
-        rand.add_css_class("control-button");
-        next.add_css_class("control-button");
-        toggle_play.add_css_class("control-button");
-        prev.add_css_class("control-button");
-        repeat.add_css_class("control-button");
+        let button_class = if compact {
+            "playlist-button"
+        } else {
+            "control-button"
+        };
+        rand.add_css_class(button_class);
+        next.add_css_class(button_class);
+        toggle_play.add_css_class(button_class);
+        prev.add_css_class(button_class);
+        repeat.add_css_class(button_class);
+        prev_chapter.add_css_class(button_class);
+        next_chapter.add_css_class(button_class);
+        rand.set_visible(!compact);
+        repeat.set_visible(!compact);
+        prev_chapter.set_tooltip_text(Some("Previous chapter"));
+        next_chapter.set_tooltip_text(Some("Next chapter"));
+        prev_chapter.set_visible(false);
+        next_chapter.set_visible(false);
         controls.append(&rand);
+        controls.append(&prev_chapter);
         controls.append(&prev);
         controls.append(&toggle_play);
         controls.append(&next);
+        controls.append(&next_chapter);
         controls.append(&repeat);
         control_box.append(&progress_bar);
         control_box.append(&controls);
@@ -140,6 +210,14 @@ impl SimpleComponent for ControlModel {
             sender.input(ControlEvents::Next);
         }));
 
+        prev_chapter.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(ControlEvents::PrevChapter);
+        }));
+
+        next_chapter.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(ControlEvents::NextChapter);
+        }));
+
         repeat.connect_clicked(clone!(@strong sender => move |_| {
             sender.input(ControlEvents::Repeat);
         }));
@@ -157,24 +235,36 @@ impl SimpleComponent for ControlModel {
                 @weak song_artist_label,
                 @weak toggle_play,
                 @weak rand,
-                @weak repeat
+                @weak repeat,
+                @strong sender
                 => @default-return ControlFlow::Break, move || {
                     let current_status_option = Yap::status();
                     let current_song_info_option = Yap::current();
-                    if let Some(current_song_info) = current_song_info_option {
-                        song_name_label.set_label(&to_twenty_char(current_song_info.0.name));
-                        song_artist_label.set_label(&current_song_info.0.artist);
-                        progress_bar.set_value(current_song_info.1.perc as f64);
+                    if let Some((song, time)) = current_song_info_option {
+                        sender.input(ControlEvents::Progress(
+                            song.name.clone(),
+                            time.min * 60 + time.sec,
+                            time.tot_min * 60 + time.tot_sec,
+                        ));
+                        let shown = cleanup::clean(&song);
+                        song_name_label.set_label(&if compact {
+                            shown.name
+                        } else {
+                            to_twenty_char(shown.name)
+                        });
+                        song_artist_label.set_label(&shown.artist);
+                        progress_bar.set_value(time.perc as f64);
                     } else {
-                        song_name_label.set_label("\t\t\t");
+                        sender.input(ControlEvents::Progress(String::new(), 0, 0));
+                        song_name_label.set_label(if compact { "" } else { "\t\t\t" });
                         song_artist_label.set_label("");
                         progress_bar.set_value(0.);
                     }
                     if let Ok(current_status) = current_status_option {
                         toggle_play.set_label(if current_status.is_paused {
-                            ""
+                            glyphs::PLAY
                         } else {
-                            "󰏤"
+                            glyphs::PAUSE
                         });
                         repeat.set_class_active("active", current_status.repeat);
                         rand.set_class_active("active", current_status.random);
@@ -186,20 +276,29 @@ impl SimpleComponent for ControlModel {
 
         ComponentParts {
             model: ControlModel {
+                layout,
                 is_paused: current_status.is_paused,
                 is_repeating: current_status.repeat,
                 is_random: current_status.random,
                 current_song: "".to_string(),
+                sidecars: Sidecars::index(&config.general.music_directory()),
+                chapters: vec![],
+                elapsed: 0,
+                duration: 0,
             },
             widgets: ControlWidgets {
                 play_button: toggle_play,
                 random_button: rand,
                 repeat_button: repeat,
+                progress_bar,
+                prev_chapter_button: prev_chapter,
+                next_chapter_button: next_chapter,
+                marked_song: String::new(),
             },
         }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
             ControlEvents::Toggle => {
                 if self.is_paused {
@@ -220,14 +319,61 @@ impl SimpleComponent for ControlModel {
                 Yap::repeat();
                 self.is_repeating = !self.is_repeating
             }
+            ControlEvents::Progress(song, elapsed, duration) => {
+                if song != self.current_song {
+                    // Songs can be renamed or downloaded while running.
+                    if self.sidecars.path(&song).is_none() {
+                        self.sidecars.refresh();
+                    }
+                    self.chapters = self
+                        .sidecars
+                        .get(&song)
+                        .and_then(|info| info.chapters)
+                        .unwrap_or_default();
+                    let _ = sender.output(ControlOutput::SongChanged(song.clone()));
+                    self.current_song = song;
+                }
+                self.elapsed = elapsed;
+                self.duration = duration;
+            }
+            ControlEvents::NextChapter => {
+                let elapsed = self.elapsed as f64;
+                if let Some(chapter) = self
+                    .chapters
+                    .iter()
+                    .find(|chapter| chapter.start_time > elapsed + 1.)
+                {
+                    seek_to(chapter.start_time);
+                }
+            }
+            ControlEvents::PrevChapter => {
+                // Like the previous-track button: go back to the start of the
+                // current chapter first, and to the one before only when
+                // already near its start.
+                let elapsed = self.elapsed as f64;
+                let current = self
+                    .chapters
+                    .iter()
+                    .rposition(|chapter| chapter.start_time <= elapsed);
+                let target = match current {
+                    Some(index) if elapsed - self.chapters[index].start_time < 3. => {
+                        index.saturating_sub(1)
+                    }
+                    Some(index) => index,
+                    None => 0,
+                };
+                if let Some(chapter) = self.chapters.get(target) {
+                    seek_to(chapter.start_time);
+                }
+            }
         }
     }
 
     fn update_view(&self, widgets: &mut Self::Widgets, _sender: ComponentSender<Self>) {
         if self.is_paused {
-            widgets.play_button.set_label("")
+            widgets.play_button.set_label(glyphs::PLAY)
         } else {
-            widgets.play_button.set_label("󰏤")
+            widgets.play_button.set_label(glyphs::PAUSE)
         }
         widgets
             .repeat_button
@@ -235,6 +381,40 @@ impl SimpleComponent for ControlModel {
         widgets
             .random_button
             .set_class_active("active", self.is_random);
+        if widgets.marked_song != self.current_song {
+            widgets.progress_bar.clear_marks();
+            if self.duration > 0 {
+                for chapter in &self.chapters {
+                    widgets.progress_bar.add_mark(
+                        chapter.start_time / self.duration as f64 * 100.,
+                        gtk::PositionType::Bottom,
+                        None,
+                    );
+                }
+            }
+            let show_chapters = self.layout == ControlLayout::Full && !self.chapters.is_empty();
+            widgets.prev_chapter_button.set_visible(show_chapters);
+            widgets.next_chapter_button.set_visible(show_chapters);
+            widgets.marked_song = self.current_song.clone();
+        }
+        let chapter = self
+            .chapters
+            .iter()
+            .rev()
+            .find(|chapter| chapter.start_time <= self.elapsed as f64);
+        widgets
+            .progress_bar
+            .set_tooltip_text(chapter.map(|chapter| chapter.title.as_str()));
+    }
+}
+
+/// Seeks within the current song. `yap seek` only takes whole percentages,
+/// which is too coarse to land on a chapter in a long mix.
+fn seek_to(seconds: f64) {
+    if let Err(error) =
+        Mpd::connect().and_then(|mut mpd| mpd.command(&format!("seekcur {}", seconds)))
+    {
+        println!("Error while seeking: {}", error);
     }
 }
 
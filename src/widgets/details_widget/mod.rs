@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+
+use gtk::gio;
+use gtk::prelude::*;
+use relm4::{gtk, Component, ComponentParts, ComponentSender, RelmWidgetExt};
+
+use crate::glyphs;
+use crate::library::{details::SongDetails, SongRecord};
+
+pub struct DetailsModel {
+    details: Option<SongDetails>,
+    music_directory: PathBuf,
+    /// Counts the requests, so a slow load can't replace a later one.
+    requested: usize,
+}
+
+#[derive(Debug)]
+pub enum DetailsEvents {
+    /// Loads the details of the record, with the sidecar found for its name
+    /// in case there's none next to the file.
+    Show(SongRecord, Option<PathBuf>),
+    Close,
+    OpenFolder,
+    CopyUrl,
+}
+
+#[relm4::component(pub)]
+impl Component for DetailsModel {
+    type Input = DetailsEvents;
+    type Output = ();
+    type Init = PathBuf;
+    type CommandOutput = (usize, SongDetails);
+
+    view! {
+        gtk::Revealer {
+            set_transition_type: gtk::RevealerTransitionType::SlideLeft,
+            #[watch]
+            set_reveal_child: model.details.is_some(),
+
+            gtk::ScrolledWindow {
+                set_policy: (gtk::PolicyType::Never, gtk::PolicyType::Automatic),
+                set_vexpand: true,
+
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_width_request: 300,
+                    set_spacing: 5,
+                    set_margin_all: 10,
+                    add_css_class: "song-details",
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+
+                        gtk::Label {
+                            set_hexpand: true,
+                            set_halign: gtk::Align::Start,
+                            set_wrap: true,
+                            add_css_class: "details-title",
+                            #[watch]
+                            set_label: model.field(|details| Some(details.name.clone())).as_str(),
+                        },
+
+                        gtk::Button {
+                            set_label: glyphs::CLOSE,
+                            add_css_class: "playlist-button",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(DetailsEvents::Close);
+                            },
+                        },
+                    },
+
+                    gtk::Label {
+                        set_halign: gtk::Align::Start,
+                        #[watch]
+                        set_label: model.field(|details| Some(details.artist.clone())).as_str(),
+                    },
+
+                    #[name = "fields"]
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 5,
+                    },
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 10,
+                        set_margin_top: 10,
+
+                        gtk::Button {
+                            set_label: "Open folder",
+                            #[watch]
+                            set_sensitive: model.details.as_ref().is_some_and(|details| details.path.is_some()),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(DetailsEvents::OpenFolder);
+                            },
+                        },
+
+                        gtk::Button {
+                            set_label: "Copy URL",
+                            #[watch]
+                            set_sensitive: model.details.as_ref().is_some_and(|details| details.url.is_some()),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(DetailsEvents::CopyUrl);
+                            },
+                        },
+                    },
+                }
+            }
+        }
+    }
+
+    fn init(
+        music_directory: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = DetailsModel {
+            details: None,
+            music_directory,
+            requested: 0,
+        };
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            DetailsEvents::Show(record, sidecar) => {
+                self.requested += 1;
+                let request = self.requested;
+                let music_directory = self.music_directory.clone();
+                sender.spawn_oneshot_command(move || {
+                    (
+                        request,
+                        SongDetails::load(&record, sidecar, &music_directory),
+                    )
+                });
+            }
+            DetailsEvents::Close => {
+                self.requested += 1;
+                self.details = None;
+            }
+            DetailsEvents::OpenFolder => {
+                let folder = self
+                    .details
+                    .as_ref()
+                    .and_then(|details| details.path.as_ref())
+                    .and_then(|path| path.parent());
+                if let Some(folder) = folder {
+                    let uri = gio::File::for_path(folder).uri();
+                    if let Err(error) =
+                        gio::AppInfo::launch_default_for_uri(&uri, None::<&gio::AppLaunchContext>)
+                    {
+                        println!("Couldn't open {}: {}", folder.display(), error);
+                    }
+                }
+            }
+            DetailsEvents::CopyUrl => {
+                let url = self
+                    .details
+                    .as_ref()
+                    .and_then(|details| details.url.as_ref());
+                if let (Some(url), Some(display)) = (url, gtk::gdk::Display::default()) {
+                    display.clipboard().set_text(url);
+                }
+            }
+        }
+    }
+
+    fn update_cmd_with_view(
+        &mut self,
+        widgets: &mut Self::Widgets,
+        (request, details): Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        if request != self.requested {
+            return;
+        }
+        while let Some(child) = widgets.fields.first_child() {
+            widgets.fields.remove(&child);
+        }
+        for (name, value) in fields(&details) {
+            widgets.fields.append(
+                &gtk::Label::builder()
+                    .halign(gtk::Align::Start)
+                    .css_classes(["details-name"])
+                    .label(name)
+                    .build(),
+            );
+            widgets.fields.append(
+                &gtk::Label::builder()
+                    .halign(gtk::Align::Start)
+                    .wrap(true)
+                    .selectable(true)
+                    .label(value.as_deref().unwrap_or("Unknown"))
+                    .build(),
+            );
+        }
+        self.details = Some(details);
+        self.update_view(widgets, sender);
+    }
+}
+
+/// The labeled fields of the panel, in order.
+fn fields(details: &SongDetails) -> [(&'static str, Option<String>); 11] {
+    [
+        (
+            "File",
+            details.path.as_ref().map(|path| path.display().to_string()),
+        ),
+        (
+            "Duration",
+            details
+                .duration
+                .map(|d| format!("{}:{:02}", d / 60, d % 60)),
+        ),
+        (
+            "Format",
+            match (&details.codec, details.bitrate) {
+                (Some(codec), Some(bitrate)) => Some(format!("{} {} kbit/s", codec, bitrate)),
+                (Some(codec), None) => Some(codec.clone()),
+                (None, Some(bitrate)) => Some(format!("{} kbit/s", bitrate)),
+                (None, None) => None,
+            },
+        ),
+        (
+            "Size",
+            details
+                .size
+                .map(|size| format!("{:.1} MB", size as f64 / 1_000_000.)),
+        ),
+        ("Downloaded", details.downloaded_at.clone()),
+        ("Video ID", details.video_id.clone()),
+        ("URL", details.url.clone()),
+        ("Channel", details.channel.clone()),
+        ("Uploaded", details.upload_date.clone()),
+        (
+            "Tags",
+            (!details.tags.is_empty()).then(|| details.tags.join(", ")),
+        ),
+        ("Description", details.description.clone()),
+    ]
+}
+
+impl DetailsModel {
+    fn field(&self, value: impl Fn(&SongDetails) -> Option<String>) -> String {
+        self.details
+            .as_ref()
+            .and_then(value)
+            .unwrap_or_else(|| "Unknown".to_string())
+    }
+}
@@ -0,0 +1,140 @@
+use gtk::prelude::*;
+use relm4::{gtk, ComponentParts, ComponentSender, RelmWidgetExt, SimpleComponent};
+
+use crate::doctor::{self, Check};
+use crate::glyphs;
+use crate::Config;
+
+pub struct DiagnosticsModel {
+    config: Config,
+    checks: Vec<Check>,
+}
+
+#[derive(Debug)]
+pub enum DiagnosticsEvents {
+    Run,
+    Opened,
+}
+
+pub struct DiagnosticsWidgets {
+    list: gtk::ListBox,
+}
+
+impl SimpleComponent for DiagnosticsModel {
+    type Input = DiagnosticsEvents;
+    type Output = ();
+    type Init = Config;
+    type Root = gtk::Box;
+    type Widgets = DiagnosticsWidgets;
+
+    fn init_root() -> Self::Root {
+        gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .vexpand(true)
+            .build()
+    }
+
+    fn init(
+        init: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let title_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .build();
+        let title = gtk::Label::builder()
+            .label("Diagnostics")
+            .name("title")
+            .hexpand(true)
+            .halign(gtk::Align::Start)
+            .margin_top(10)
+            .margin_bottom(10)
+            .margin_start(10)
+            .build();
+        let run = gtk::Button::with_label(glyphs::RELOAD);
+        run.set_tooltip_text(Some("Run checks again"));
+        run.add_css_class("playlist-button");
+        let run_sender = sender.clone();
+        run.connect_clicked(move |_| run_sender.input(DiagnosticsEvents::Run));
+        // The checks spawn processes and connect to MPD, so they only run
+        // once the page is first shown.
+        root.connect_map(move |_| sender.input(DiagnosticsEvents::Opened));
+        title_box.append(&title);
+        title_box.append(&run);
+        let scrolled_window = gtk::ScrolledWindow::builder().vexpand(true).build();
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        list.add_css_class("not-transparent");
+        scrolled_window.set_child(Some(&list));
+        root.append(&title_box);
+        root.append(&scrolled_window);
+
+        let model = DiagnosticsModel {
+            config: init,
+            checks: vec![],
+        };
+        let widgets = DiagnosticsWidgets { list };
+        build_list(&widgets.list, &model.checks);
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            DiagnosticsEvents::Run => self.checks = doctor::run(&self.config),
+            DiagnosticsEvents::Opened => {
+                if self.checks.is_empty() {
+                    self.checks = doctor::run(&self.config);
+                }
+            }
+        }
+    }
+
+    fn update_view(&self, widgets: &mut Self::Widgets, _sender: ComponentSender<Self>) {
+        build_list(&widgets.list, &self.checks);
+    }
+}
+
+fn build_list(list: &gtk::ListBox, checks: &[Check]) {
+    while let Some(row) = list.row_at_index(0) {
+        list.remove(&row);
+    }
+    for check in checks {
+        let row = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(20)
+            .build();
+        let status = gtk::Label::new(Some(if check.passed {
+            glyphs::CHECK
+        } else {
+            glyphs::CLOSE
+        }));
+        status.add_css_class("song-label");
+        status.set_class_active("delete", !check.passed);
+        let text = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .hexpand(true)
+            .build();
+        let name = gtk::Label::builder()
+            .label(&format!("{}: {}", check.name, check.detail))
+            .halign(gtk::Align::Start)
+            .wrap(true)
+            .build();
+        name.add_css_class("song-label");
+        text.append(&name);
+        if !check.passed {
+            let hint = gtk::Label::builder()
+                .label(&check.hint)
+                .halign(gtk::Align::Start)
+                .wrap(true)
+                .build();
+            text.append(&hint);
+        }
+        row.append(&status);
+        row.append(&text);
+        let list_box_row = gtk::ListBoxRow::builder().child(&row).build();
+        list_box_row.add_css_class("list-row");
+        list_box_row.add_css_class("song-list-row");
+        list.append(&list_box_row);
+    }
+}
@@ -0,0 +1,282 @@
+use std::{path::PathBuf, time::Duration};
+
+use gtk::gio;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::glib::signal::Propagation;
+use gtk::prelude::*;
+use gtk4_layer_shell::{Edge, Layer, LayerShell};
+use relm4::{gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller};
+
+use super::control_widget::{ControlLayout, ControlModel, ControlOutput};
+use super::yap_widget::{load_css, YapModel};
+use crate::cover::ArtSource;
+use crate::glyphs;
+use crate::state::{Corner, MiniPlayerState};
+use crate::thumbnails;
+use crate::Config;
+
+const MARGIN: i32 = 20;
+/// Width and height of the album art.
+const ART_SIZE: i32 = 80;
+
+pub struct MiniPlayerModel {
+    window: gtk::Window,
+    art: gtk::Image,
+    /// The song whose art is shown or being generated.
+    song: String,
+    config: Config,
+    state: MiniPlayerState,
+    overlay: Option<Controller<YapModel>>,
+    /// Shares the playback polling and transport handling of the full player.
+    _controls: Controller<ControlModel>,
+    /// Whether a save of the new window size is already scheduled.
+    resize_pending: bool,
+    /// Watches for miniatures fetched while their song plays.
+    _miniatures: Option<gio::FileMonitor>,
+}
+
+#[derive(Debug)]
+pub enum MiniPlayerEvents {
+    SongChanged(String),
+    MiniatureWritten(PathBuf),
+    MoveCorner,
+    Expand,
+    Resized,
+    SaveSize,
+    Close,
+}
+
+impl Component for MiniPlayerModel {
+    type Input = MiniPlayerEvents;
+    type Output = ();
+    type Init = Config;
+    /// The thumbnail of a song's art, generated off the main thread.
+    type CommandOutput = (String, Option<PathBuf>);
+    type Root = gtk::Window;
+    type Widgets = ();
+
+    fn init_root() -> Self::Root {
+        gtk::Window::builder().title("YAP-GUI").build()
+    }
+
+    fn init(
+        config: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        load_css();
+        let state = MiniPlayerState::load();
+        root.set_default_size(state.width, state.height);
+        if !config.runtime.window_mode {
+            root.init_layer_shell();
+            root.set_layer(Layer::Overlay);
+            apply_corner(root, state.corner);
+        }
+
+        let hbox = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        hbox.add_css_class("not-transparent");
+        hbox.add_css_class("mini-player");
+        let art = gtk::Image::builder()
+            .width_request(ART_SIZE)
+            .height_request(ART_SIZE)
+            .tooltip_text("Open the full player")
+            .build();
+        let expand = gtk::GestureClick::new();
+        expand.connect_released(clone!(@strong sender => move |_, _, _, _| {
+            sender.input(MiniPlayerEvents::Expand);
+        }));
+        art.add_controller(expand);
+        let controls = ControlModel::builder()
+            .launch((config.clone(), ControlLayout::Compact))
+            .forward(sender.input_sender(), |output| match output {
+                ControlOutput::SongChanged(song) => MiniPlayerEvents::SongChanged(song),
+            });
+        controls.widget().set_hexpand(true);
+        let window_buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+        let corner = gtk::Button::with_label(glyphs::CORNER);
+        corner.set_tooltip_text(Some("Move to the next corner"));
+        let close = gtk::Button::with_label(glyphs::CLOSE);
+        for button in [&corner, &close] {
+            button.add_css_class("playlist-button");
+            window_buttons.append(button);
+        }
+        hbox.append(&art);
+        hbox.append(controls.widget());
+        hbox.append(&window_buttons);
+        root.set_child(Some(&hbox));
+
+        corner.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(MiniPlayerEvents::MoveCorner);
+        }));
+        close.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(MiniPlayerEvents::Close);
+        }));
+        root.connect_close_request(clone!(@strong sender => move |_| {
+            sender.input(MiniPlayerEvents::Close);
+            Propagation::Stop
+        }));
+        root.connect_default_width_notify(clone!(@strong sender => move |_| {
+            sender.input(MiniPlayerEvents::Resized);
+        }));
+        root.connect_default_height_notify(clone!(@strong sender => move |_| {
+            sender.input(MiniPlayerEvents::Resized);
+        }));
+
+        let miniatures = watch_miniatures(&config, &sender);
+        let model = MiniPlayerModel {
+            window: root.clone(),
+            art,
+            song: String::new(),
+            config,
+            state,
+            overlay: None,
+            _controls: controls,
+            resize_pending: false,
+            _miniatures: miniatures,
+        };
+        ComponentParts { model, widgets: () }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            MiniPlayerEvents::SongChanged(song) => {
+                self.song = song;
+                self.load_art(&sender);
+            }
+            MiniPlayerEvents::MiniatureWritten(path) => {
+                // Replaces the cover or placeholder shown until now.
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy());
+                if stem.is_some_and(|stem| stem == self.song) {
+                    self.load_art(&sender);
+                }
+            }
+            MiniPlayerEvents::MoveCorner => {
+                self.state.corner = self.state.corner.next();
+                if !self.config.runtime.window_mode {
+                    apply_corner(&self.window, self.state.corner);
+                }
+                self.save_size();
+            }
+            MiniPlayerEvents::Expand => match &self.overlay {
+                Some(overlay) => overlay.widget().present(),
+                None => {
+                    let overlay = YapModel::builder().launch(self.config.clone()).detach();
+                    relm4::main_application().add_window(overlay.widget());
+                    overlay.widget().present();
+                    self.overlay = Some(overlay);
+                }
+            },
+            MiniPlayerEvents::Resized => {
+                // Resizing notifies for every frame, so save once it settles.
+                if !self.resize_pending {
+                    self.resize_pending = true;
+                    glib::timeout_add_local_once(
+                        Duration::from_millis(500),
+                        clone!(@strong sender => move || {
+                            sender.input(MiniPlayerEvents::SaveSize);
+                        }),
+                    );
+                }
+            }
+            MiniPlayerEvents::SaveSize => {
+                self.resize_pending = false;
+                self.save_size();
+            }
+            MiniPlayerEvents::Close => {
+                self.save_size();
+                relm4::main_application().quit();
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        (song, thumbnail): Self::CommandOutput,
+        _sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        // A slow thumbnail of a song that has since been skipped is dropped.
+        if song == self.song {
+            self.art.set_from_file(thumbnail.as_ref());
+        }
+    }
+}
+
+impl MiniPlayerModel {
+    /// Shows the art of the current song once its thumbnail is ready.
+    fn load_art(&self, sender: &ComponentSender<Self>) {
+        if self.song.is_empty() {
+            self.art.clear();
+            return;
+        }
+        let song = self.song.clone();
+        let miniature_directory = self.config.general.miniature_directory();
+        let music_directory = self.config.general.music_directory();
+        sender.spawn_oneshot_command(move || {
+            let source = ArtSource {
+                miniature: thumbnails::miniature_path(&miniature_directory, &song),
+                audio: None,
+                music_directory,
+                title: song.clone(),
+            };
+            let thumbnail = source
+                .resolve()
+                .and_then(|source| thumbnails::thumbnail(&source, ART_SIZE));
+            (song, thumbnail)
+        });
+    }
+
+    /// Remembers the size of the mini-player window along with its corner.
+    fn save_size(&mut self) {
+        if self.window.width() > 0 && self.window.height() > 0 {
+            self.state.width = self.window.width();
+            self.state.height = self.window.height();
+        }
+        self.state.save();
+    }
+}
+
+/// Reports miniatures once they are completely written or moved into the
+/// miniature directory.
+fn watch_miniatures(
+    config: &Config,
+    sender: &ComponentSender<MiniPlayerModel>,
+) -> Option<gio::FileMonitor> {
+    let directory = config.general.miniature_directory();
+    let monitor = match gio::File::for_path(&directory)
+        .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+    {
+        Ok(monitor) => monitor,
+        Err(error) => {
+            println!("Couldn't watch {}: {}", directory.display(), error);
+            return None;
+        }
+    };
+    monitor.connect_changed(clone!(@strong sender => move |_, file, other, event| {
+        let written = match event {
+            gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::MovedIn => Some(file),
+            gio::FileMonitorEvent::Renamed => other,
+            _ => None,
+        };
+        if let Some(path) = written.and_then(|file| file.path()) {
+            sender.input(MiniPlayerEvents::MiniatureWritten(path));
+        }
+    }));
+    Some(monitor)
+}
+
+fn apply_corner(window: &gtk::Window, corner: Corner) {
+    window.set_anchor(Edge::Top, corner.is_top());
+    window.set_anchor(Edge::Bottom, !corner.is_top());
+    window.set_anchor(Edge::Left, corner.is_left());
+    window.set_anchor(Edge::Right, !corner.is_left());
+    for edge in [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right] {
+        window.set_margin(edge, MARGIN);
+    }
+}
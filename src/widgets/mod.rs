@@ -1,8 +1,15 @@
+pub mod cleanup_widget;
 pub mod control_widget;
+pub mod details_widget;
+pub mod diagnostics_widget;
+pub mod mini_player_widget;
 pub mod music_widget;
+pub mod output_widget;
 pub mod playlist_widget;
 pub mod power_menu_widget;
 pub mod queue_widget;
 pub mod song_widget;
+pub mod standalone_widget;
+pub mod toast_widget;
 pub mod volume_widget;
 pub mod yap_widget;
@@ -4,24 +4,44 @@ use relm4::{
     SimpleComponent,
 };
 
+use super::cleanup_widget::{CleanupModel, CleanupOutput};
+use super::diagnostics_widget::DiagnosticsModel;
 use super::playlist_widget::PlaylistModel;
-use super::song_widget::SongModel;
+use super::song_widget::{SongEvent, SongModel, SongOutput};
+use super::toast_widget::UndoAction;
+use crate::yap_cli::yap_cli::Song;
+use crate::Config;
 
 #[derive(Debug)]
 pub enum MusicEvents {
     StartWidget,
+    Deleted(UndoAction),
+    RestoreSong(Song),
+    Navigate(String),
+    Search(String),
+    ReloadSongs,
+    Error(String),
+}
+
+#[derive(Debug)]
+pub enum MusicOutput {
+    Undoable(UndoAction),
+    Error(String),
 }
 
 pub struct MusicModel {
     song_widget: Controller<SongModel>,
     playlist_widget: Controller<PlaylistModel>,
+    diagnostics_widget: Controller<DiagnosticsModel>,
+    cleanup_widget: Controller<CleanupModel>,
+    stack: gtk::Stack,
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for MusicModel {
     type Input = MusicEvents;
-    type Output = ();
-    type Init = String;
+    type Output = MusicOutput;
+    type Init = Config;
 
     view! {
         #[root]
@@ -39,12 +59,14 @@ impl SimpleComponent for MusicModel {
                 #[name = "sidebar"]
                 gtk::StackSidebar {},
 
-                #[name = "stack"]
-                gtk::Stack {
+                #[local_ref]
+                stack -> gtk::Stack {
                     add_css_class: "not-transparent",
 
                     add_titled: (model.song_widget.widget(), Some("songs"), "Songs"),
                     add_titled: (model.playlist_widget.widget(), Some("playlists"), "Playlists"),
+                    add_titled: (model.cleanup_widget.widget(), Some("cleanup"), "Clean-up"),
+                    add_titled: (model.diagnostics_widget.widget(), Some("diagnostics"), "Diagnostics"),
                 }
             }
         }
@@ -55,18 +77,61 @@ impl SimpleComponent for MusicModel {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> relm4::ComponentParts<Self> {
-        let song_widget = SongModel::builder()
-            .launch(init)
-            .forward(sender.input_sender(), |()| MusicEvents::StartWidget);
+        let diagnostics_widget = DiagnosticsModel::builder().launch(init.clone()).detach();
+        let cleanup_widget =
+            CleanupModel::builder()
+                .launch(init.clone())
+                .forward(sender.input_sender(), |output| match output {
+                    CleanupOutput::Applied => MusicEvents::ReloadSongs,
+                    CleanupOutput::Error(error) => MusicEvents::Error(error),
+                });
         let playlist_widget = PlaylistModel::builder()
-            .launch(())
+            .launch(init.database.clone())
             .forward(sender.input_sender(), |()| MusicEvents::StartWidget);
+        let song_widget = SongModel::builder().launch(init).forward(
+            sender.input_sender(),
+            |output| match output {
+                SongOutput::Deleted(action) => MusicEvents::Deleted(action),
+                SongOutput::Error(error) => MusicEvents::Error(error),
+            },
+        );
         let model = MusicModel {
             song_widget,
             playlist_widget,
+            diagnostics_widget,
+            cleanup_widget,
+            stack: gtk::Stack::new(),
         };
+        let stack = &model.stack;
         let widgets = view_output!();
-        widgets.sidebar.set_stack(&widgets.stack);
+        widgets.sidebar.set_stack(stack);
         ComponentParts { model, widgets }
     }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            MusicEvents::StartWidget => {}
+            // A standalone library window has no toast to undo from.
+            MusicEvents::Deleted(action) => {
+                let _ = sender.output(MusicOutput::Undoable(action));
+            }
+            MusicEvents::RestoreSong(song) => self.song_widget.emit(SongEvent::Restore(song)),
+            MusicEvents::Navigate(page) => {
+                if self.stack.child_by_name(&page).is_some() {
+                    self.stack.set_visible_child_name(&page);
+                }
+            }
+            MusicEvents::Search(query) => {
+                self.stack.set_visible_child_name("songs");
+                self.song_widget.emit(SongEvent::SetSearch(query));
+            }
+            MusicEvents::ReloadSongs => self.song_widget.emit(SongEvent::Reload),
+            MusicEvents::Error(error) => {
+                // The standalone library window has no toast to show it in.
+                if sender.output(MusicOutput::Error(error.clone())).is_err() {
+                    println!("{}", error);
+                }
+            }
+        }
+    }
 }
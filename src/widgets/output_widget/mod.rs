@@ -0,0 +1,165 @@
+use glib::clone;
+use gtk::glib;
+use gtk::prelude::*;
+use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
+
+use crate::glyphs;
+use crate::mpd::{Mpd, Output};
+use crate::volume::{SharedBackend, Sink};
+
+/// Application name MPD's PulseAudio output registers with the server.
+const MPD_APPLICATION: &str = "Music Player Daemon";
+
+pub struct OutputModel {
+    backend: SharedBackend,
+    outputs: Vec<Output>,
+    sinks: Vec<Sink>,
+}
+
+#[derive(Debug)]
+pub enum OutputEvents {
+    Refresh,
+    ToggleOutput(u32, bool),
+    MoveToSink(u32),
+}
+
+pub struct OutputWidgets {
+    list: gtk::Box,
+    /// The outputs and sinks the list was last built from.
+    shown: Option<(Vec<Output>, Vec<Sink>)>,
+}
+
+impl SimpleComponent for OutputModel {
+    type Input = OutputEvents;
+    type Output = ();
+    type Init = SharedBackend;
+    type Root = gtk::MenuButton;
+    type Widgets = OutputWidgets;
+
+    fn init_root() -> Self::Root {
+        gtk::MenuButton::builder()
+            .label(glyphs::OUTPUT)
+            .tooltip_text("Audio output")
+            .css_classes(["playlist-button"])
+            .build()
+    }
+
+    fn init(
+        backend: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let list = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(5)
+            .build();
+        let popover = gtk::Popover::builder().child(&list).build();
+        popover.connect_show(clone!(@strong sender => move |_| {
+            sender.input(OutputEvents::Refresh);
+        }));
+        root.set_popover(Some(&popover));
+
+        let model = OutputModel {
+            backend,
+            outputs: vec![],
+            sinks: vec![],
+        };
+        ComponentParts {
+            model,
+            widgets: OutputWidgets { list, shown: None },
+        }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        let result = match message {
+            OutputEvents::Refresh => Ok(()),
+            OutputEvents::ToggleOutput(id, enabled) => {
+                Mpd::connect().and_then(|mut mpd| mpd.set_output_enabled(id, enabled))
+            }
+            OutputEvents::MoveToSink(sink) => self
+                .backend
+                .borrow_mut()
+                .move_stream(&self.mpd_streams(), sink),
+        };
+        if let Err(error) = result {
+            println!("Error while changing audio output: {}", error);
+        }
+        self.outputs = Mpd::connect()
+            .and_then(|mut mpd| mpd.outputs())
+            .unwrap_or_default();
+        self.sinks = self
+            .backend
+            .borrow_mut()
+            .sinks(&self.mpd_streams())
+            .unwrap_or_default();
+    }
+
+    fn update_view(&self, widgets: &mut Self::Widgets, sender: ComponentSender<Self>) {
+        let current = (self.outputs.clone(), self.sinks.clone());
+        if widgets.shown.as_ref() == Some(&current) {
+            return;
+        }
+        widgets.shown = Some(current);
+        while let Some(child) = widgets.list.first_child() {
+            widgets.list.remove(&child);
+        }
+
+        widgets.list.append(&section_title("MPD outputs"));
+        for output in &self.outputs {
+            let check = gtk::CheckButton::builder()
+                .label(&format!("{} ({})", output.name, output.plugin))
+                .active(output.enabled)
+                .build();
+            let id = output.id;
+            check.connect_toggled(clone!(@strong sender => move |check| {
+                sender.input(OutputEvents::ToggleOutput(id, check.is_active()));
+            }));
+            widgets.list.append(&check);
+        }
+
+        if !self.sinks.is_empty() {
+            widgets.list.append(&section_title(&format!(
+                "{} devices",
+                self.backend.borrow().name()
+            )));
+            let mut group: Option<gtk::CheckButton> = None;
+            for sink in &self.sinks {
+                let radio = gtk::CheckButton::builder()
+                    .label(&sink.description)
+                    .active(sink.active)
+                    .build();
+                radio.set_group(group.as_ref());
+                group.get_or_insert_with(|| radio.clone());
+                let index = sink.index;
+                radio.connect_toggled(clone!(@strong sender => move |radio| {
+                    if radio.is_active() {
+                        sender.input(OutputEvents::MoveToSink(index));
+                    }
+                }));
+                widgets.list.append(&radio);
+            }
+        }
+    }
+}
+
+impl OutputModel {
+    /// Names MPD's stream can have on the sound server: each PulseAudio output
+    /// names its stream after itself.
+    fn mpd_streams(&self) -> Vec<String> {
+        self.outputs
+            .iter()
+            .filter(|output| output.plugin == "pulse")
+            .map(|output| output.name.clone())
+            .chain([MPD_APPLICATION.to_string()])
+            .collect()
+    }
+}
+
+fn section_title(title: &str) -> gtk::Label {
+    let label = gtk::Label::builder()
+        .label(title)
+        .halign(gtk::Align::Start)
+        .build();
+    label.add_css_class("output-title");
+    label
+}
@@ -1,4 +1,7 @@
+use crate::glyphs;
+use crate::library;
 use crate::yap_cli::yap_cli::Yap;
+use crate::Database;
 
 use gtk::prelude::*;
 use relm4::{
@@ -22,7 +25,7 @@ pub enum PlaylistEvents {
 impl SimpleComponent for PlaylistModel {
     type Input = PlaylistEvents;
     type Output = ();
-    type Init = ();
+    type Init = Database;
 
     view! {
         gtk::Box {
@@ -43,11 +46,11 @@ impl SimpleComponent for PlaylistModel {
     }
 
     fn init(
-        _: Self::Init,
+        database: Self::Init,
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> relm4::ComponentParts<Self> {
-        let playlists = Yap::get_playlists();
+        let playlists = library::playlists(&database);
         let mut playlist_entries = FactoryVecDeque::builder()
             .launch(gtk::ListBox::default())
             .forward(sender.input_sender(), |output| match output {
@@ -122,7 +125,7 @@ impl FactoryComponent for PlaylistEntry {
                 },
 
                 gtk::Button {
-                    set_label: "󰆴",
+                    set_label: glyphs::DELETE,
                     add_css_class: "playlist-button",
                     connect_clicked[sender, playlist_name = self.playlist_name.clone(), index] => move |_| {
                         sender.output(
@@ -132,7 +135,7 @@ impl FactoryComponent for PlaylistEntry {
                 },
 
                 gtk::Button {
-                    set_label: "",
+                    set_label: glyphs::PLAY,
                     add_css_class: "playlist-button",
                     connect_clicked[sender, playlist_name = self.playlist_name.clone()] => move |_| {
                         sender.output(PlaylistEntryOutput::Play(playlist_name.to_string())).unwrap();
@@ -1,21 +1,47 @@
-use std::process::Command;
+use std::time::Duration;
 
+use gtk::glib;
+use gtk::glib::{clone, ControlFlow};
 use gtk::prelude::*;
-use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
+use relm4::{gtk, Component, ComponentParts, ComponentSender};
 
-pub struct PowerMenuModel {}
+use crate::glyphs;
+use crate::mpd::{
+    self,
+    supervisor::{self, Health},
+};
+use crate::{Config, Server, Supervisor};
+
+pub struct PowerMenuModel {
+    settings: Supervisor,
+    servers: Vec<Server>,
+    /// Index in `servers` of the one in use, if it is listed there.
+    selected_server: Option<usize>,
+    health: Health,
+    restarting: bool,
+    error: Option<String>,
+}
 
 #[derive(Debug)]
 pub enum PowerMenuEvents {
     Close,
     RestartMpd,
+    RefreshHealth,
+    SelectServer(usize),
+}
+
+#[derive(Debug)]
+pub enum PowerMenuCommands {
+    Health(Health),
+    Restarted(Result<(), String>),
 }
 
 #[relm4::component(pub)]
-impl SimpleComponent for PowerMenuModel {
+impl Component for PowerMenuModel {
     type Input = PowerMenuEvents;
     type Output = ();
-    type Init = ();
+    type Init = Config;
+    type CommandOutput = PowerMenuCommands;
 
     view! {
         gtk::Box {
@@ -32,7 +58,32 @@ impl SimpleComponent for PowerMenuModel {
                 gtk::Label{
                     set_widget_name: "app-title",
                     set_label: "YAP-GUI",
-                }
+                },
+
+                gtk::Label {
+                    set_widget_name: "mpd-health",
+                    set_hexpand: true,
+                    set_halign: gtk::Align::End,
+                    set_margin_end: 20,
+                    set_wrap: true,
+                    #[watch]
+                    set_label: &model.health_label(),
+                    #[watch]
+                    set_class_active: ("delete", model.error.is_some() || !model.health.is_up()),
+                },
+
+                gtk::DropDown::from_strings(&server_names) {
+                    set_visible: model.servers.len() > 1,
+                    set_valign: gtk::Align::Center,
+                    set_margin_end: 20,
+                    set_tooltip_text: Some("MPD server"),
+                    set_selected: model
+                        .selected_server
+                        .map_or(gtk::INVALID_LIST_POSITION, |index| index as u32),
+                    connect_selected_notify[sender] => move |dropdown| {
+                        sender.input(PowerMenuEvents::SelectServer(dropdown.selected() as usize));
+                    }
+                },
             },
 
             gtk::Box {
@@ -43,7 +94,7 @@ impl SimpleComponent for PowerMenuModel {
                 set_width_request: 100,
 
                 gtk::Button::builder()
-                    .label("󰅖")
+                    .label(glyphs::CLOSE)
                     .margin_end(20)
                     .margin_bottom(20)
                     .height_request(100)
@@ -60,7 +111,7 @@ impl SimpleComponent for PowerMenuModel {
                 },
 
                 gtk::Button::builder()
-                    .label("")
+                    .label(glyphs::POWER)
                     .height_request(100)
                     .width_request(100)
                     .margin_bottom(20)
@@ -70,6 +121,8 @@ impl SimpleComponent for PowerMenuModel {
                 {
                     add_css_class: "power-button",
                     add_css_class: "not-transparent",
+                    #[watch]
+                    set_sensitive: !model.restarting && model.is_local(),
                     connect_clicked[sender] => move |_| {
                         sender.input(PowerMenuEvents::RestartMpd);
                     }
@@ -79,23 +132,113 @@ impl SimpleComponent for PowerMenuModel {
     }
 
     fn init(
-        _init: Self::Init,
+        init: Self::Init,
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let model = PowerMenuModel {};
+        let model = PowerMenuModel {
+            settings: init.supervisor,
+            selected_server: init.servers.iter().position(mpd::is_selected),
+            servers: init.servers,
+            health: supervisor::health(),
+            restarting: false,
+            error: None,
+        };
+        let server_names: Vec<&str> = model
+            .servers
+            .iter()
+            .map(|server| server.name.as_str())
+            .collect();
         let widgets = view_output!();
+
+        glib::timeout_add_local(
+            Duration::from_secs(5),
+            clone!(@strong sender => move || {
+                sender.input(PowerMenuEvents::RefreshHealth);
+                ControlFlow::Continue
+            }),
+        );
+
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
         match message {
             PowerMenuEvents::Close => relm4::main_application().quit(),
-            PowerMenuEvents::RestartMpd => restart_mpd(),
+            PowerMenuEvents::RestartMpd => {
+                if !self.restarting {
+                    self.restarting = true;
+                    self.error = None;
+                    let settings = self.settings.clone();
+                    sender.spawn_oneshot_command(move || {
+                        PowerMenuCommands::Restarted(supervisor::restart(&settings))
+                    });
+                }
+            }
+            PowerMenuEvents::RefreshHealth => {
+                sender.spawn_oneshot_command(|| PowerMenuCommands::Health(supervisor::health()));
+            }
+            PowerMenuEvents::SelectServer(index) => {
+                if let Some(server) = self.servers.get(index) {
+                    mpd::select_server(server);
+                    self.selected_server = Some(index);
+                    self.error = None;
+                    sender.input(PowerMenuEvents::RefreshHealth);
+                }
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            PowerMenuCommands::Health(health) => self.health = health,
+            PowerMenuCommands::Restarted(result) => {
+                self.restarting = false;
+                self.error = result.err();
+                sender.input(PowerMenuEvents::RefreshHealth);
+            }
         }
     }
 }
 
-fn restart_mpd() {
-    Command::new("mpd").output().unwrap();
+impl PowerMenuModel {
+    /// Whether the selected server runs on this machine, so it can be
+    /// restarted from here.
+    fn is_local(&self) -> bool {
+        self.selected_server
+            .and_then(|index| self.servers.get(index))
+            .is_none_or(|server| server.is_local())
+    }
+
+    fn health_label(&self) -> String {
+        if self.restarting {
+            return "Restarting MPD…".to_string();
+        }
+        if let Some(error) = &self.error {
+            return format!("MPD failed to start: {}", error);
+        }
+        match (&self.health.version, self.health.uptime) {
+            (Some(version), Some(uptime)) => {
+                format!("MPD {} · up {}", version, format_uptime(uptime))
+            }
+            (Some(version), None) => format!("MPD {}", version),
+            (None, _) => "MPD is down".to_string(),
+        }
+    }
+}
+
+fn format_uptime(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
 }
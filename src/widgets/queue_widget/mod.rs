@@ -2,24 +2,33 @@ use glib::clone;
 use glib::ControlFlow;
 use gtk::prelude::*;
 use relm4::{gtk, ComponentParts, ComponentSender, RelmWidgetExt, SimpleComponent};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::yap_cli::yap_cli::Song;
+use crate::cleanup;
+use crate::glyphs;
+use crate::state::QueueState;
+use crate::yap_cli::yap_cli::QueueEntry;
 use crate::yap_cli::yap_cli::Yap;
 
+use super::toast_widget::UndoAction;
+
 pub struct QueueModel {}
 
 #[derive(Debug)]
 pub enum QueueEvents {
-    RemoveFromQueue(String),
+    RemoveFromQueue(u32),
+    MoveInQueue(u32, u32),
+    PlayFromQueue(u32),
     ClearQueue,
     ShuffleQueue,
+    RestoreQueue,
+    SaveAsPlaylist(String),
 }
 
 impl SimpleComponent for QueueModel {
     type Root = gtk::Box;
     type Input = QueueEvents;
-    type Output = ();
+    type Output = UndoAction;
     type Init = ();
     type Widgets = ();
 
@@ -38,7 +47,8 @@ impl SimpleComponent for QueueModel {
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let mut songs_in_queue = Yap::get_queue();
-        let mut current_song = Yap::current();
+        let mut current_position = current_position();
+        let mut clock = TrackClock::new();
         let queue_container = gtk::Box::builder()
             .orientation(gtk::Orientation::Vertical)
             .vexpand(true)
@@ -57,23 +67,55 @@ impl SimpleComponent for QueueModel {
             .margin_bottom(10)
             .margin_start(10)
             .build();
-        let clear_queue = gtk::Button::with_label("");
-        let shuffle_queue = gtk::Button::with_label("󰒟");
+        let clear_queue = gtk::Button::with_label(glyphs::CLEAR);
+        let shuffle_queue = gtk::Button::with_label(glyphs::SHUFFLE);
+        let restore_queue = gtk::Button::with_label(glyphs::RESTORE);
+        restore_queue.set_tooltip_text(Some("Restore saved queue"));
+        restore_queue.set_visible(songs_in_queue.is_empty() && !QueueState::load().is_empty());
+        let playlist_name_entry = gtk::Entry::builder()
+            .placeholder_text("Playlist name")
+            .build();
+        let save_playlist_button = gtk::Button::with_label("Save");
+        let save_playlist_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        save_playlist_box.append(&playlist_name_entry);
+        save_playlist_box.append(&save_playlist_button);
+        let save_playlist_popover = gtk::Popover::builder()
+            .child(&save_playlist_box)
+            .build();
+        let save_queue = gtk::MenuButton::builder()
+            .label(glyphs::SAVE)
+            .tooltip_text("Save queue as playlist…")
+            .popover(&save_playlist_popover)
+            .build();
+        let queue_info = gtk::Label::builder()
+            .name("queue-info")
+            .halign(gtk::Align::Start)
+            .margin_start(10)
+            .margin_bottom(10)
+            .build();
         let separator = gtk::Separator::builder()
             .orientation(gtk::Orientation::Horizontal)
             .build();
         let queue_box = gtk::Box::builder()
             .orientation(gtk::Orientation::Vertical)
             .build();
-        let list_box = build_list(&songs_in_queue, &sender);
+        let list_box = build_list(&songs_in_queue, current_position, &sender);
         queue_box.append(&list_box);
         queue_container.add_css_class("not-transparent");
         clear_queue.add_css_class("playlist-button");
         shuffle_queue.add_css_class("playlist-button");
+        restore_queue.add_css_class("playlist-button");
+        save_queue.add_css_class("playlist-button");
         title_box.append(&title);
+        title_box.append(&restore_queue);
+        title_box.append(&save_queue);
         title_box.append(&shuffle_queue);
         title_box.append(&clear_queue);
         queue_container.append(&title_box);
+        queue_container.append(&queue_info);
         queue_container.append(&separator);
         queue_container.append(&queue_box);
         scrolled_window.set_child(Some(&queue_container));
@@ -87,20 +129,57 @@ impl SimpleComponent for QueueModel {
             sender.input(QueueEvents::ShuffleQueue);
         }));
 
+        restore_queue.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(QueueEvents::RestoreQueue);
+        }));
+
+        let save_playlist = clone!(
+            @strong sender,
+            @weak playlist_name_entry,
+            @weak save_playlist_popover
+            => move || {
+                let playlist = playlist_name_entry.text().trim().to_string();
+                if !playlist.is_empty() {
+                    sender.input(QueueEvents::SaveAsPlaylist(playlist));
+                    playlist_name_entry.set_text("");
+                    save_playlist_popover.popdown();
+                }
+            }
+        );
+        let save_playlist_activate = save_playlist.clone();
+        playlist_name_entry.connect_activate(move |_| save_playlist_activate());
+        save_playlist_button.connect_clicked(move |_| save_playlist());
+
         glib::timeout_add_local(
             Duration::from_millis(500),
             clone!(
                 @weak queue_box,
+                @weak queue_info,
+                @weak restore_queue,
                 @strong sender,
                 => @default-return ControlFlow::Break, move || {
-                    let current_song_loop = Yap::current();
+                    let status = Yap::status().ok();
+                    let current_position_loop = status.as_ref().and_then(|status| status.position);
+                    let paused = status.is_none_or(|status| status.is_paused);
                     let queue = Yap::get_queue();
-                    if songs_in_queue != queue || current_song != current_song_loop {
-                        let list = build_list(&queue, &sender);
+                    let track_remaining = clock.remaining(current_position_loop, paused);
+                    queue_info.set_label(&queue_summary(
+                        &queue,
+                        current_position_loop,
+                        track_remaining,
+                    ));
+                    if songs_in_queue != queue || current_position != current_position_loop {
+                        let list = build_list(&queue, current_position_loop, &sender);
                         queue_box.remove(&queue_box.first_child().unwrap());
                         queue_box.append(&list);
+                        if queue.is_empty() {
+                            restore_queue.set_visible(!QueueState::load().is_empty());
+                        } else {
+                            queue_state(&queue, current_position_loop).save();
+                            restore_queue.set_visible(false);
+                        }
                         songs_in_queue = queue;
-                        current_song = current_song_loop;
+                        current_position = current_position_loop;
                     }
                 }
                 ControlFlow::Continue
@@ -112,17 +191,139 @@ impl SimpleComponent for QueueModel {
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
-            QueueEvents::RemoveFromQueue(song) => Yap::remove_from_queue(&song),
-            QueueEvents::ClearQueue => Yap::clear_queue(),
+            QueueEvents::RemoveFromQueue(position) => {
+                let removed = Yap::get_queue()
+                    .into_iter()
+                    .find(|entry| entry.position == position);
+                Yap::remove_from_queue(position);
+                // A standalone queue window has no toast to undo from.
+                if let Some(entry) = removed {
+                    let _ = sender.output(UndoAction::RemoveFromQueue(entry));
+                }
+            }
+            QueueEvents::MoveInQueue(from, to) => Yap::move_in_queue(from, to),
+            QueueEvents::PlayFromQueue(position) => Yap::play_at(position),
+            QueueEvents::ClearQueue => {
+                let state = queue_state(&Yap::get_queue(), current_position());
+                Yap::clear_queue();
+                QueueState::clear();
+                if !state.is_empty() {
+                    let _ = sender.output(UndoAction::ClearQueue(state));
+                }
+            }
             QueueEvents::ShuffleQueue => Yap::shuffle_queue(),
+            QueueEvents::RestoreQueue => QueueState::load().restore(),
+            QueueEvents::SaveAsPlaylist(playlist) => Yap::save_queue_as_playlist(&playlist),
         }
     }
 }
 
-fn build_list(songs_in_queue: &Vec<Song>, sender: &ComponentSender<QueueModel>) -> gtk::ListBox {
-    let current_song = Yap::current();
+fn current_position() -> Option<u32> {
+    Yap::status().ok().and_then(|status| status.position)
+}
+
+/// Estimates how much of the current track is left between polls, so that
+/// `yap mpd current` only runs when the track, the pause state or a seek may
+/// have changed it.
+struct TrackClock {
+    position: Option<u32>,
+    paused: bool,
+    remaining: u32,
+    synced_at: Instant,
+}
+
+impl TrackClock {
+    /// Seeks change nothing else the queue polls, so resync regularly.
+    const RESYNC: Duration = Duration::from_secs(10);
+
+    fn new() -> TrackClock {
+        TrackClock {
+            position: None,
+            paused: true,
+            remaining: 0,
+            synced_at: Instant::now(),
+        }
+    }
+
+    fn remaining(&mut self, position: Option<u32>, paused: bool) -> u32 {
+        if position != self.position
+            || paused != self.paused
+            || self.synced_at.elapsed() >= Self::RESYNC
+        {
+            self.position = position;
+            self.paused = paused;
+            self.remaining = Yap::current().map_or(0, |(_, time)| time.remaining_seconds());
+            self.synced_at = Instant::now();
+        }
+        if self.paused {
+            self.remaining
+        } else {
+            self.remaining
+                .saturating_sub(self.synced_at.elapsed().as_secs() as u32)
+        }
+    }
+}
+
+/// Describes the part of the queue that is still to be played: number of
+/// tracks, remaining duration and the time at which it will finish.
+fn queue_summary(
+    queue: &[QueueEntry],
+    current_position: Option<u32>,
+    track_remaining: u32,
+) -> String {
+    let remaining: Vec<&QueueEntry> = queue
+        .iter()
+        .filter(|entry| current_position.is_none_or(|current| entry.position >= current))
+        .collect();
+    let mut remaining_seconds: u32 = remaining
+        .iter()
+        .filter(|entry| Some(entry.position) != current_position)
+        .map(|entry| entry.duration)
+        .sum();
+    if current_position.is_some() {
+        remaining_seconds += track_remaining;
+    }
+    let tracks = match remaining.len() {
+        1 => "1 track".to_string(),
+        count => format!("{} tracks", count),
+    };
+    let finish = glib::DateTime::now_local()
+        .and_then(|now| now.add_seconds(remaining_seconds as f64))
+        .and_then(|end| end.format("%H:%M"));
+    match finish {
+        Ok(finish) if remaining_seconds > 0 => format!(
+            "{} · {} left · ends at {}",
+            tracks,
+            format_duration(remaining_seconds),
+            finish
+        ),
+        _ => tracks,
+    }
+}
+
+fn format_duration(seconds: u32) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn queue_state(queue: &[QueueEntry], position: Option<u32>) -> QueueState {
+    QueueState {
+        songs: queue.iter().map(|entry| entry.song.clone()).collect(),
+        position,
+    }
+}
+
+fn build_list(
+    songs_in_queue: &Vec<QueueEntry>,
+    current_position: Option<u32>,
+    sender: &ComponentSender<QueueModel>,
+) -> gtk::ListBox {
     let list_box = gtk::ListBox::builder()
         .name("queue_list")
         .vexpand(true)
@@ -131,13 +332,25 @@ fn build_list(songs_in_queue: &Vec<Song>, sender: &ComponentSender<QueueModel>)
         .build();
     list_box.add_css_class("not-transparent");
     list_box.add_css_class("queue");
-    for song in songs_in_queue {
-        let song_name = &song.name;
-        let song_artist = &song.artist;
+    // A row index only means something within this list, so play the queue
+    // position of the entry that was rendered in the row.
+    let positions: Vec<u32> = songs_in_queue.iter().map(|entry| entry.position).collect();
+    list_box.connect_row_activated(clone!(@strong sender => move |_, row| {
+        let position = usize::try_from(row.index())
+            .ok()
+            .and_then(|index| positions.get(index));
+        if let Some(position) = position {
+            sender.input(QueueEvents::PlayFromQueue(*position));
+        }
+    }));
+    let last_position = songs_in_queue.last().map(|entry| entry.position);
+    for entry in songs_in_queue {
+        let position = entry.position;
+        let shown = cleanup::clean(&entry.song);
         let list_box_row = gtk::ListBoxRow::builder()
             .hexpand(true)
             .height_request(30)
-            .name(song_name)
+            .name(&position.to_string())
             .build();
         list_box_row.add_css_class("list-row");
         let hbox: gtk::Box = gtk::Box::builder()
@@ -146,25 +359,40 @@ fn build_list(songs_in_queue: &Vec<Song>, sender: &ComponentSender<QueueModel>)
             .height_request(30)
             .build();
         let label_song = gtk::Label::builder()
-            .label(&format!("{} - {}", song_name, song_artist))
+            .label(&format!("{} - {}", shown.name, shown.artist))
             .hexpand(true)
             .halign(gtk::Align::Start)
             .build();
-        let delete_button = gtk::Button::with_label("󰆴");
+        let label_duration = gtk::Label::builder()
+            .label(&format_duration(entry.duration))
+            .margin_end(10)
+            .build();
+        label_duration.add_css_class("queue-duration");
+        let up_button = gtk::Button::with_label(glyphs::UP);
+        up_button.add_css_class("playlist-button");
+        up_button.set_sensitive(position > 0);
+        let down_button = gtk::Button::with_label(glyphs::DOWN);
+        down_button.add_css_class("playlist-button");
+        down_button.set_sensitive(Some(position) != last_position);
+        let delete_button = gtk::Button::with_label(glyphs::DELETE);
         delete_button.add_css_class("playlist-button");
         delete_button.add_css_class("delete");
         hbox.append(&label_song);
+        hbox.append(&label_duration);
+        hbox.append(&up_button);
+        hbox.append(&down_button);
         hbox.append(&delete_button);
-        delete_button.connect_clicked(clone!(@strong sender, @strong song_name => move |_| {
-            sender.input(QueueEvents::RemoveFromQueue(song_name.to_string()));
+        up_button.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(QueueEvents::MoveInQueue(position, position - 1));
+        }));
+        down_button.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(QueueEvents::MoveInQueue(position, position + 1));
+        }));
+        delete_button.connect_clicked(clone!(@strong sender => move |_| {
+            sender.input(QueueEvents::RemoveFromQueue(position));
         }));
 
-        if current_song.is_some() {
-            list_box_row.set_class_active(
-                "current-song",
-                current_song.as_ref().unwrap().0.name == song.name,
-            );
-        }
+        list_box_row.set_class_active("current-song", current_position == Some(position));
         list_box_row.set_child(Some(&hbox));
         list_box.append(&list_box_row);
     }
@@ -1,10 +1,22 @@
 use std::{
-    fs::File,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use crate::yap_cli::yap_cli::Yap;
+use crate::cleanup;
+use crate::cover::ArtSource;
+use crate::glyphs;
+use crate::library::{self, info::Sidecars, tags, SongRecord};
+use crate::miniatures::{Fetcher, Progress};
+use crate::thumbnails;
+use crate::trash::{song_files, Trash};
+use crate::yap_cli::yap_cli::{Song, Yap};
+use crate::{Config, Database};
 
+use super::details_widget::{DetailsEvents, DetailsModel};
+use super::toast_widget::UndoAction;
+
+use gtk::glib::{self, ControlFlow};
 use gtk::prelude::*;
 use relm4::{
     factory::FactoryVecDeque,
@@ -12,119 +24,448 @@ use relm4::{
     FactorySender,
 };
 
-use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
+use relm4::{
+    gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
+    RelmWidgetExt, SimpleComponent,
+};
 
 #[derive(Debug)]
 pub enum SongEvent {
     Play(String),
     Delete(String, DynamicIndex),
     AddToQueue(String),
+    PlayNext(String),
+    Restore(Song),
+    Search(String),
+    SetSearch(String),
+    ShowDetails(Song),
+    Edit(DynamicIndex, String, Song, bool),
+    Reload,
+    Miniature(Progress),
+    RetryMiniatures,
+}
+
+#[derive(Debug)]
+pub enum SongOutput {
+    Deleted(UndoAction),
+    Error(String),
 }
 
 pub struct SongModel {
     songs: FactoryVecDeque<SongEntry>,
+    records: Vec<SongRecord>,
+    details: Controller<DetailsModel>,
+    sidecars: Sidecars,
+    search_entry: gtk::SearchEntry,
+    play_keeps_queue: bool,
+    write_tags: bool,
+    database: Database,
+    music_directory: PathBuf,
+    miniature_directory: PathBuf,
+    fetcher: Option<Fetcher>,
+    miniatures_queued: usize,
+    miniatures_done: usize,
+    /// Songs whose miniature couldn't be fetched, kept for the retry button.
+    miniatures_failed: Vec<String>,
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for SongModel {
     type Input = SongEvent;
-    type Output = ();
-    type Init = String;
+    type Output = SongOutput;
+    type Init = Config;
 
     view! {
         gtk::Box {
-            set_orientation: gtk::Orientation::Vertical,
+            set_orientation: gtk::Orientation::Horizontal,
             set_vexpand: true,
 
-            gtk::ScrolledWindow{
-                set_vexpand: true,
-                set_policy: (gtk::PolicyType::Automatic, gtk::PolicyType::Automatic),
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_hexpand: true,
 
                 #[local_ref]
-                songs_list -> gtk::ListBox {
+                search_entry -> gtk::SearchEntry {
+                    set_placeholder_text: Some("Search songs"),
+                    set_margin_all: 10,
+                    connect_search_changed[sender] => move |entry| {
+                        sender.input(SongEvent::Search(entry.text().to_string()));
+                    },
+                },
+
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 10,
+                    set_margin_start: 10,
+                    set_margin_end: 10,
+                    #[watch]
+                    set_visible: model.miniatures_done < model.miniatures_queued
+                        || !model.miniatures_failed.is_empty(),
+
+                    gtk::Label {
+                        add_css_class: "song-label",
+                        #[watch]
+                        set_label: &model.miniature_status(),
+                    },
+
+                    gtk::ProgressBar {
+                        set_hexpand: true,
+                        set_valign: gtk::Align::Center,
+                        #[watch]
+                        set_fraction: model.miniatures_done as f64 / model.miniatures_queued.max(1) as f64,
+                    },
+
+                    gtk::Button {
+                        set_label: glyphs::RELOAD,
+                        set_tooltip_text: Some("Retry the failed miniatures"),
+                        add_css_class: "song-button",
+                        #[watch]
+                        set_visible: !model.miniatures_failed.is_empty(),
+                        connect_clicked[sender] => move |_| {
+                            sender.input(SongEvent::RetryMiniatures);
+                        },
+                    },
+                },
+
+                gtk::ScrolledWindow{
                     set_vexpand: true,
-                    add_css_class: "not-transparent"
+                    set_policy: (gtk::PolicyType::Automatic, gtk::PolicyType::Automatic),
+
+                    #[local_ref]
+                    songs_list -> gtk::ListBox {
+                        set_vexpand: true,
+                        add_css_class: "not-transparent"
+                    }
                 }
-            }
+            },
+
+            model.details.widget(),
         }
     }
 
     fn init(
-        minuatures_directory: Self::Init,
+        config: Self::Init,
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> relm4::ComponentParts<Self> {
-        let songs = Yap::get_songs();
+        let music_directory = config.general.music_directory();
+        let miniature_directory = config.general.miniature_directory();
+        let retention = Duration::from_secs(config.general.trash_retention_days * 24 * 60 * 60);
+        Trash::purge(&music_directory, retention);
+        // Trashed songs stay in the yap database until they are purged, so
+        // don't wait for the next start to purge them.
+        let purged_directory = music_directory.clone();
+        glib::timeout_add_seconds_local(60 * 60, move || {
+            Trash::purge(&purged_directory, retention);
+            ControlFlow::Continue
+        });
+        let trash = Trash::load(&music_directory);
+        let songs = library::songs(&config.database);
         let mut song_entries = FactoryVecDeque::builder()
             .launch(gtk::ListBox::default())
             .forward(sender.input_sender(), |output| match output {
                 SongEntryOutput::AddToQueue(song) => SongEvent::AddToQueue(song),
+                SongEntryOutput::PlayNext(song) => SongEvent::PlayNext(song),
                 SongEntryOutput::Play(song) => SongEvent::Play(song),
                 SongEntryOutput::Delete(song, index) => SongEvent::Delete(song, index),
-            });
-        for song in songs {
-            let img_path = {
-                let path = Path::new(&minuatures_directory).join(format!("{}.jpg", song.name));
-                if File::open(path.clone()).is_ok() {
-                    Some(path)
-                } else {
-                    None
+                SongEntryOutput::Details(song) => SongEvent::ShowDetails(song),
+                SongEntryOutput::Edit(index, song_name, song, write_tags) => {
+                    SongEvent::Edit(index, song_name, song, write_tags)
                 }
-            };
-
+            });
+        for record in songs.iter().filter(|record| !trash.contains(&record.song)) {
             song_entries.guard().push_back(SongEntryInit {
-                song_name: song.name,
-                song_artist: song.artist,
-                img_path,
+                song_name: record.song.name.clone(),
+                song_artist: record.song.artist.clone(),
+                art: art_source(&miniature_directory, &music_directory, record),
+                write_tags: config.general.write_tags,
             });
         }
+        let missing: Vec<String> = if config.general.download_miniature {
+            songs
+                .iter()
+                .filter(|record| !trash.contains(&record.song))
+                .filter(|record| {
+                    thumbnails::miniature_path(&miniature_directory, &record.song.name).is_none()
+                })
+                .map(|record| record.song.name.clone())
+                .collect()
+        } else {
+            vec![]
+        };
+        let fetcher = (!missing.is_empty()).then(|| {
+            let input = sender.input_sender().clone();
+            let fetcher = Fetcher::spawn(
+                config.general.miniature_command.clone(),
+                miniature_directory.clone(),
+                move |progress| input.emit(SongEvent::Miniature(progress)),
+            );
+            for song_name in &missing {
+                fetcher.queue(song_name.clone());
+            }
+            fetcher
+        });
         let model = SongModel {
             songs: song_entries,
+            records: songs,
+            details: DetailsModel::builder()
+                .launch(music_directory.clone())
+                .detach(),
+            sidecars: Sidecars::index(&music_directory),
+            search_entry: gtk::SearchEntry::new(),
+            play_keeps_queue: config.general.play_keeps_queue,
+            write_tags: config.general.write_tags,
+            database: config.database,
+            music_directory,
+            miniature_directory,
+            fetcher,
+            miniatures_queued: missing.len(),
+            miniatures_done: 0,
+            miniatures_failed: vec![],
         };
         let songs_list = model.songs.widget();
+        let search_entry = &model.search_entry;
         let widgets = view_output!();
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
-            SongEvent::Play(song) => Yap::play_song(&song),
-            SongEvent::Delete(song, index) => {
-                Yap::delete_song(&song);
-                self.songs.guard().remove(index.current_index());
+            SongEvent::Play(song) => {
+                if self.play_keeps_queue {
+                    Yap::play_now(&song)
+                } else {
+                    Yap::play_song(&song)
+                }
+            }
+            SongEvent::Delete(_, index) => {
+                if let Some(entry) = self.songs.guard().remove(index.current_index()) {
+                    let song = Song {
+                        name: entry.song_name,
+                        artist: entry.song_artist,
+                    };
+                    Trash::trash_song(&self.music_directory, &self.record(&song));
+                    sender
+                        .output(SongOutput::Deleted(UndoAction::DeleteSong(song)))
+                        .unwrap();
+                }
             }
             SongEvent::AddToQueue(song) => Yap::add_to_queue(&song),
+            SongEvent::PlayNext(song) => Yap::play_next(&song),
+            SongEvent::Restore(song) => {
+                Trash::restore_song(&self.music_directory, &song);
+                let art = art_source(
+                    &self.miniature_directory,
+                    &self.music_directory,
+                    &self.record(&song),
+                );
+                self.songs.guard().push_back(SongEntryInit {
+                    song_name: song.name,
+                    song_artist: song.artist,
+                    art,
+                    write_tags: self.write_tags,
+                });
+            }
+            SongEvent::Search(query) => self.songs.broadcast(SongEntryInput::Filter(query)),
+            SongEvent::SetSearch(query) => self.search_entry.set_text(&query),
+            SongEvent::ShowDetails(song) => {
+                let sidecar = self.sidecars.path(&song.name).cloned();
+                self.details
+                    .emit(DetailsEvents::Show(self.record(&song), sidecar));
+            }
+            SongEvent::Edit(index, song_name, song, write_tags) => {
+                if let Err(error) = Yap::edit_song(&song_name, &song) {
+                    sender
+                        .output(SongOutput::Error(format!(
+                            "Couldn't rename {}: {}",
+                            song_name, error
+                        )))
+                        .unwrap();
+                    return;
+                }
+                if let Err(error) = library::move_song_files(
+                    &song_name,
+                    &song.name,
+                    &self.miniature_directory,
+                    &mut self.sidecars,
+                ) {
+                    sender.output(SongOutput::Error(error)).unwrap();
+                }
+                let record = self
+                    .records
+                    .iter_mut()
+                    .find(|record| record.song.name == song_name);
+                if write_tags {
+                    let path = match record.as_ref().and_then(|record| record.path.as_ref()) {
+                        Some(path) => Some(self.music_directory.join(path)),
+                        None => song_files(&self.music_directory, &song_name)
+                            .into_iter()
+                            .next(),
+                    };
+                    let written = match path {
+                        Some(path) => tags::write(&path, &song),
+                        None => Err(format!("No audio file found for {}", song_name)),
+                    };
+                    if let Err(error) = written {
+                        sender.output(SongOutput::Error(error)).unwrap();
+                    }
+                }
+                let record = match record {
+                    Some(record) => {
+                        record.song = song.clone();
+                        record.clone()
+                    }
+                    None => SongRecord::from(song.clone()),
+                };
+                // The row's buttons hold on to the song name, so the row is
+                // rebuilt rather than relabeled.
+                let art = art_source(&self.miniature_directory, &self.music_directory, &record);
+                let position = index.current_index();
+                let mut songs = self.songs.guard();
+                songs.remove(position);
+                songs.insert(
+                    position,
+                    SongEntryInit {
+                        song_name: song.name,
+                        song_artist: song.artist,
+                        art,
+                        write_tags,
+                    },
+                );
+            }
+            SongEvent::Reload => {
+                let trash = Trash::load(&self.music_directory);
+                self.records = library::songs(&self.database);
+                self.sidecars.refresh();
+                let mut songs = self.songs.guard();
+                songs.clear();
+                for record in self
+                    .records
+                    .iter()
+                    .filter(|record| !trash.contains(&record.song))
+                {
+                    songs.push_back(SongEntryInit {
+                        song_name: record.song.name.clone(),
+                        song_artist: record.song.artist.clone(),
+                        art: art_source(&self.miniature_directory, &self.music_directory, record),
+                        write_tags: self.write_tags,
+                    });
+                }
+                drop(songs);
+                self.songs
+                    .broadcast(SongEntryInput::Filter(self.search_entry.text().to_string()));
+            }
+            SongEvent::Miniature(Progress::Fetched(song_name, path)) => {
+                self.miniatures_done += 1;
+                let index = self
+                    .songs
+                    .iter()
+                    .position(|entry| entry.song_name == song_name);
+                if let Some(index) = index {
+                    self.songs.send(index, SongEntryInput::Miniature(path));
+                }
+            }
+            SongEvent::Miniature(Progress::Failed(song_name, error)) => {
+                println!("Couldn't fetch the miniature of {}: {}", song_name, error);
+                self.miniatures_done += 1;
+                self.miniatures_failed.push(song_name);
+            }
+            SongEvent::RetryMiniatures => {
+                if let Some(fetcher) = &self.fetcher {
+                    self.miniatures_done -= self.miniatures_failed.len();
+                    for song_name in self.miniatures_failed.drain(..) {
+                        fetcher.queue(song_name);
+                    }
+                }
+            }
         }
     }
 }
 
+impl SongModel {
+    /// The library record of `song`, or a bare one if the library doesn't
+    /// know it.
+    fn record(&self, song: &Song) -> SongRecord {
+        self.records
+            .iter()
+            .find(|record| &record.song == song)
+            .cloned()
+            .unwrap_or_else(|| SongRecord::from(song.clone()))
+    }
+
+    fn miniature_status(&self) -> String {
+        if self.miniatures_failed.is_empty() {
+            format!(
+                "Fetching miniatures {}/{}",
+                self.miniatures_done, self.miniatures_queued
+            )
+        } else {
+            format!(
+                "Fetching miniatures {}/{}, {} failed",
+                self.miniatures_done,
+                self.miniatures_queued,
+                self.miniatures_failed.len()
+            )
+        }
+    }
+}
+
+fn art_source(
+    miniature_directory: &Path,
+    music_directory: &Path,
+    record: &SongRecord,
+) -> ArtSource {
+    ArtSource {
+        miniature: thumbnails::miniature_path(miniature_directory, &record.song.name),
+        audio: record.path.as_ref().map(|path| music_directory.join(path)),
+        music_directory: music_directory.to_path_buf(),
+        title: record.song.name.clone(),
+    }
+}
+
+/// Width and height of the miniatures in the song list.
+const THUMBNAIL_SIZE: i32 = 150;
+
 #[derive(Debug)]
 pub struct SongEntry {
     song_name: String,
     song_artist: String,
-    img_path: Option<PathBuf>,
+    write_tags: bool,
+    /// The song as shown, after the clean-up rules.
+    shown: Song,
+    visible: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum SongEntryInput {
+    Filter(String),
+    Miniature(PathBuf),
 }
 
 #[derive(Debug)]
 pub enum SongEntryOutput {
     AddToQueue(String),
+    PlayNext(String),
     Play(String),
     Delete(String, DynamicIndex),
+    Details(Song),
+    Edit(DynamicIndex, String, Song, bool),
 }
 
 pub struct SongEntryInit {
     song_name: String,
     song_artist: String,
-    img_path: Option<PathBuf>,
+    art: ArtSource,
+    write_tags: bool,
 }
 
 #[relm4::factory(pub)]
 impl FactoryComponent for SongEntry {
     type ParentWidget = gtk::ListBox;
-    type Input = ();
+    type Input = SongEntryInput;
     type Output = SongEntryOutput;
     type Init = SongEntryInit;
-    type CommandOutput = ();
+    type CommandOutput = Option<PathBuf>;
 
     view! {
         #[root]
@@ -134,28 +475,31 @@ impl FactoryComponent for SongEntry {
             .css_classes(["song-list-row", "list-row"])
             .build()
         {
+            #[watch]
+            set_visible: self.visible,
+
             gtk::Box {
                 set_orientation: gtk::Orientation::Horizontal,
                 set_hexpand: true,
                 set_height_request: 30,
 
+                #[name = "image"]
                 gtk::Image {
                     set_width_request: 150,
                     set_height_request: 150,
                     set_margin_end: 20,
                     set_margin_start: 20,
-                    set_from_file: self.img_path.clone()
                 },
 
                 gtk::Label {
                     set_halign: gtk::Align::Start,
                     set_hexpand: true,
                     add_css_class: "song-label",
-                    set_label: &format!("{} - {}", &self.song_name, &self.song_artist),
+                    set_label: &format!("{} - {}", &self.shown.name, &self.shown.artist),
                 },
 
                 gtk::Button {
-                    set_label: "󰐒",
+                    set_label: glyphs::ADD_TO_QUEUE,
                     add_css_class: "song-button",
                     connect_clicked[sender, song_name = self.song_name.clone()] => move |_| {
                         sender.output(SongEntryOutput::AddToQueue(song_name.to_string())).unwrap();
@@ -163,7 +507,89 @@ impl FactoryComponent for SongEntry {
                 },
 
                 gtk::Button {
-                    set_label: "󰆴",
+                    set_label: glyphs::PLAY_NEXT,
+                    set_tooltip_text: Some("Play next"),
+                    add_css_class: "song-button",
+                    connect_clicked[sender, song_name = self.song_name.clone()] => move |_| {
+                        sender.output(SongEntryOutput::PlayNext(song_name.to_string())).unwrap();
+                    },
+                },
+
+                gtk::Button {
+                    set_label: glyphs::DETAILS,
+                    set_tooltip_text: Some("Details"),
+                    add_css_class: "song-button",
+                    connect_clicked[sender, song = self.song()] => move |_| {
+                        sender.output(SongEntryOutput::Details(song.clone())).unwrap();
+                    },
+                },
+
+                gtk::MenuButton {
+                    set_label: glyphs::EDIT,
+                    set_tooltip_text: Some("Edit title and artist"),
+                    add_css_class: "song-button",
+
+                    #[wrap(Some)]
+                    set_popover = &gtk::Popover {
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            set_spacing: 5,
+
+                            #[name = "title_entry"]
+                            gtk::Entry {
+                                set_placeholder_text: Some("Title"),
+                                set_text: &self.song_name,
+                            },
+
+                            #[name = "artist_entry"]
+                            gtk::Entry {
+                                set_placeholder_text: Some("Artist"),
+                                set_text: &self.song_artist,
+                            },
+
+                            #[name = "write_tags_button"]
+                            gtk::CheckButton {
+                                set_label: Some("Write tags to the audio file"),
+                                set_active: self.write_tags,
+                            },
+
+                            gtk::Button {
+                                set_label: "Save",
+                                connect_clicked[
+                                    sender,
+                                    index,
+                                    song_name = self.song_name.clone(),
+                                    title_entry,
+                                    artist_entry,
+                                    write_tags_button
+                                ] => move |button| {
+                                    let song = Song {
+                                        name: title_entry.text().trim().to_string(),
+                                        artist: artist_entry.text().trim().to_string(),
+                                    };
+                                    if song.name.is_empty() {
+                                        return;
+                                    }
+                                    if let Some(popover) = button
+                                        .ancestor(gtk::Popover::static_type())
+                                        .and_downcast::<gtk::Popover>()
+                                    {
+                                        popover.popdown();
+                                    }
+                                    sender.output(SongEntryOutput::Edit(
+                                        index.clone(),
+                                        song_name.clone(),
+                                        song,
+                                        write_tags_button.is_active(),
+                                    )).unwrap();
+                                },
+                            },
+                        },
+                    },
+                },
+
+                gtk::Button {
+                    set_label: glyphs::DELETE,
                     add_css_class: "song-button",
                     connect_clicked[sender, song_name = self.song_name.clone(), index] => move |_| {
                         sender.output(
@@ -173,7 +599,7 @@ impl FactoryComponent for SongEntry {
                 },
 
                 gtk::Button {
-                    set_label: "",
+                    set_label: glyphs::PLAY,
                     add_css_class: "song-button",
                     connect_clicked[sender, song_name = self.song_name.clone()] => move |_| {
                         sender.output(SongEntryOutput::Play(song_name.to_string())).unwrap();
@@ -183,11 +609,60 @@ impl FactoryComponent for SongEntry {
         }
     }
 
-    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+    fn init_model(init: Self::Init, _index: &DynamicIndex, sender: FactorySender<Self>) -> Self {
+        let art = init.art;
+        sender.spawn_oneshot_command(move || {
+            art.resolve()
+                .and_then(|source| thumbnails::thumbnail(&source, THUMBNAIL_SIZE))
+        });
+        let shown = cleanup::clean(&Song {
+            name: init.song_name.clone(),
+            artist: init.song_artist.clone(),
+        });
         Self {
+            shown,
             song_name: init.song_name,
             song_artist: init.song_artist,
-            img_path: init.img_path,
+            write_tags: init.write_tags,
+            visible: true,
+        }
+    }
+
+    fn update_cmd_with_view(
+        &mut self,
+        widgets: &mut Self::Widgets,
+        thumbnail: Self::CommandOutput,
+        _sender: FactorySender<Self>,
+    ) {
+        widgets.image.set_from_file(thumbnail.as_ref());
+    }
+
+    fn update(&mut self, message: Self::Input, sender: FactorySender<Self>) {
+        match message {
+            SongEntryInput::Miniature(source) => {
+                sender
+                    .spawn_oneshot_command(move || thumbnails::thumbnail(&source, THUMBNAIL_SIZE));
+            }
+            SongEntryInput::Filter(query) => {
+                let query = query.to_lowercase();
+                self.visible = [
+                    &self.song_name,
+                    &self.song_artist,
+                    &self.shown.name,
+                    &self.shown.artist,
+                ]
+                .iter()
+                .any(|field| field.to_lowercase().contains(&query));
+            }
+        }
+    }
+}
+
+impl SongEntry {
+    fn song(&self) -> Song {
+        Song {
+            name: self.song_name.clone(),
+            artist: self.song_artist.clone(),
         }
     }
 }
@@ -0,0 +1,98 @@
+use gtk::prelude::*;
+use gtk4_layer_shell::{Layer, LayerShell};
+use relm4::{
+    gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
+    SimpleComponent,
+};
+
+use super::control_widget::{ControlLayout, ControlModel};
+use super::music_widget::MusicModel;
+use super::queue_widget::QueueModel;
+use super::volume_widget::VolumeModel;
+use super::yap_widget::load_css;
+use crate::Config;
+
+/// A component that can be launched on its own with `--widget`.
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum StandaloneWidget {
+    Controls,
+    Queue,
+    Library,
+    Volume,
+}
+
+impl StandaloneWidget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StandaloneWidget::Controls => "controls",
+            StandaloneWidget::Queue => "queue",
+            StandaloneWidget::Library => "library",
+            StandaloneWidget::Volume => "volume",
+        }
+    }
+}
+
+enum StandaloneController {
+    Controls(Controller<ControlModel>),
+    Queue(Controller<QueueModel>),
+    Library(Controller<MusicModel>),
+    Volume(Controller<VolumeModel>),
+}
+
+pub struct StandaloneModel {
+    _widget: StandaloneController,
+}
+
+impl SimpleComponent for StandaloneModel {
+    type Input = ();
+    type Output = ();
+    type Init = (Config, StandaloneWidget);
+    type Root = gtk::Window;
+    type Widgets = ();
+
+    fn init_root() -> Self::Root {
+        gtk::Window::builder().title("YAP-GUI").build()
+    }
+
+    fn init(
+        (config, widget): Self::Init,
+        root: &Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        load_css();
+        if !config.runtime.window_mode {
+            root.init_layer_shell();
+            root.set_layer(Layer::Overlay);
+        }
+        let controller = match widget {
+            StandaloneWidget::Controls => StandaloneController::Controls(
+                ControlModel::builder()
+                    .launch((config, ControlLayout::Full))
+                    .detach(),
+            ),
+            StandaloneWidget::Queue => {
+                StandaloneController::Queue(QueueModel::builder().launch(()).detach())
+            }
+            StandaloneWidget::Library => {
+                StandaloneController::Library(MusicModel::builder().launch(config).detach())
+            }
+            StandaloneWidget::Volume => {
+                StandaloneController::Volume(VolumeModel::builder().launch(config.volume).detach())
+            }
+        };
+        match &controller {
+            StandaloneController::Controls(controller) => root.set_child(Some(controller.widget())),
+            StandaloneController::Queue(controller) => root.set_child(Some(controller.widget())),
+            StandaloneController::Library(controller) => root.set_child(Some(controller.widget())),
+            StandaloneController::Volume(controller) => root.set_child(Some(controller.widget())),
+        }
+        root.add_css_class("standalone");
+
+        ComponentParts {
+            model: StandaloneModel {
+                _widget: controller,
+            },
+            widgets: (),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use glib::clone;
+use glib::ControlFlow;
+use gtk::prelude::*;
+use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
+
+use crate::state::QueueState;
+use crate::yap_cli::yap_cli::{QueueEntry, Song};
+
+const UNDO_TIMEOUT: Duration = Duration::from_secs(10);
+const ERROR_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum UndoAction {
+    DeleteSong(Song),
+    ClearQueue(QueueState),
+    RemoveFromQueue(QueueEntry),
+}
+
+impl UndoAction {
+    fn description(&self) -> String {
+        match self {
+            UndoAction::DeleteSong(song) => format!("Deleted {}", song.name),
+            UndoAction::ClearQueue(_) => "Cleared queue".to_string(),
+            UndoAction::RemoveFromQueue(entry) => {
+                format!("Removed {} from queue", entry.song.name)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ToastEvents {
+    Push(UndoAction),
+    /// Shows a failure, without an undo button, on top of the undo stack.
+    Error(String),
+    Undo,
+    Tick,
+}
+
+#[derive(Debug)]
+pub enum ToastOutput {
+    Undo(UndoAction),
+}
+
+pub struct ToastModel {
+    stack: Vec<(UndoAction, Instant)>,
+    errors: Vec<(String, Instant)>,
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for ToastModel {
+    type Input = ToastEvents;
+    type Output = ToastOutput;
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::Revealer {
+            set_transition_type: gtk::RevealerTransitionType::SlideUp,
+            #[watch]
+            set_reveal_child: !model.stack.is_empty() || !model.errors.is_empty(),
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_halign: gtk::Align::Center,
+                set_margin_top: 20,
+                add_css_class: "not-transparent",
+                add_css_class: "toast",
+
+                gtk::Label {
+                    add_css_class: "toast-label",
+                    #[watch]
+                    set_label: &model.label(),
+                },
+
+                gtk::Button {
+                    set_label: "Undo",
+                    add_css_class: "playlist-button",
+                    #[watch]
+                    set_visible: model.errors.is_empty(),
+                    connect_clicked[sender] => move |_| {
+                        sender.input(ToastEvents::Undo);
+                    }
+                },
+            }
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        glib::timeout_add_local(
+            Duration::from_millis(1000),
+            clone!(@strong sender => move || {
+                sender.input(ToastEvents::Tick);
+                ControlFlow::Continue
+            }),
+        );
+        let model = ToastModel {
+            stack: vec![],
+            errors: vec![],
+        };
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            ToastEvents::Push(action) => self.stack.push((action, Instant::now())),
+            ToastEvents::Error(error) => self.errors.push((error, Instant::now())),
+            ToastEvents::Undo => {
+                if let Some((action, _)) = self.stack.pop() {
+                    sender.output(ToastOutput::Undo(action)).unwrap();
+                }
+            }
+            ToastEvents::Tick => {
+                self.stack
+                    .retain(|(_, pushed_at)| pushed_at.elapsed() < UNDO_TIMEOUT);
+                self.errors
+                    .retain(|(_, pushed_at)| pushed_at.elapsed() < ERROR_TIMEOUT);
+            }
+        }
+    }
+}
+
+impl ToastModel {
+    fn label(&self) -> String {
+        if let Some((error, _)) = self.errors.last() {
+            return error.clone();
+        }
+        match self.stack.last() {
+            Some((action, pushed_at)) => {
+                let remaining = UNDO_TIMEOUT.saturating_sub(pushed_at.elapsed());
+                format!(
+                    "{} — undo within {}s",
+                    action.description(),
+                    remaining.as_secs()
+                )
+            }
+            None => String::new(),
+        }
+    }
+}
@@ -1,20 +1,52 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use gtk::glib;
 use gtk::glib::signal::Propagation;
+use gtk::glib::{clone, ControlFlow};
 use gtk::prelude::*;
-use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
-use std::process::{Command, Stdio};
+use relm4::{
+    gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
+    RelmWidgetExt, SimpleComponent,
+};
+
+use super::output_widget::OutputModel;
+
+use crate::glyphs;
+use crate::volume::{self, MpdBackend, SharedBackend, VolumeBackend};
+use crate::{Volume, VolumeTarget};
 
 #[derive(Debug)]
 pub enum VolumeEvents {
     Change(f64),
+    ToggleMute,
+    ToggleTarget,
+    Scroll(f64),
+    Refresh,
+    /// Sets the volume if given, then sends the current one back.
+    Remote(Option<f64>, VolumeReply),
 }
 
-pub struct VolumeModel {}
+pub type VolumeReply = mpsc::Sender<Result<f64, String>>;
+
+pub struct VolumeModel {
+    master: SharedBackend,
+    mpd: MpdBackend,
+    target: VolumeTarget,
+    volume: f64,
+    muted: bool,
+    scroll_step: f64,
+    show_percentage: bool,
+    outputs: Controller<OutputModel>,
+}
 
 #[relm4::component(pub)]
 impl SimpleComponent for VolumeModel {
     type Input = VolumeEvents;
     type Output = ();
-    type Init = ();
+    type Init = Volume;
 
     view! {
         #[root]
@@ -26,6 +58,15 @@ impl SimpleComponent for VolumeModel {
             set_margin_bottom: 20,
             add_css_class: "not-transparent",
 
+            add_controller = gtk::EventControllerScroll {
+                set_flags: gtk::EventControllerScrollFlags::VERTICAL,
+                set_propagation_phase: gtk::PropagationPhase::Capture,
+                connect_scroll[sender] => move |_, _, dy| {
+                    sender.input(VolumeEvents::Scroll(dy));
+                    Propagation::Stop
+                }
+            },
+
             gtk::Box {
                 set_orientation: gtk::Orientation::Vertical,
                 set_vexpand: true,
@@ -39,65 +80,170 @@ impl SimpleComponent for VolumeModel {
                     set_vexpand: true,
                     set_hexpand: true,
                     set_inverted: true,
-                    set_value: get_current_volume(),
+                    #[watch]
+                    set_value: model.volume,
+                    #[watch]
+                    set_class_active: ("muted", model.muted),
 
                     connect_change_value[sender] => move |_, _, value| {
                         sender.input(VolumeEvents::Change(value));
                         Propagation::Proceed
                     }
-                }
+                },
+
+                gtk::Label {
+                    add_css_class: "volume-percentage",
+                    set_visible: model.show_percentage,
+                    #[watch]
+                    set_label: &format!("{}%", model.volume.round()),
+                },
+
+                gtk::Button {
+                    add_css_class: "playlist-button",
+                    #[watch]
+                    set_label: if model.muted { glyphs::MUTED } else { glyphs::VOLUME },
+                    #[watch]
+                    set_class_active: ("active", model.muted),
+                    connect_clicked[sender] => move |_| {
+                        sender.input(VolumeEvents::ToggleMute);
+                    }
+                },
+
+                gtk::Button {
+                    add_css_class: "playlist-button",
+                    #[watch]
+                    set_label: match model.target {
+                        VolumeTarget::Master => glyphs::SYSTEM_VOLUME,
+                        VolumeTarget::Mpd => glyphs::MPD_VOLUME,
+                    },
+                    #[watch]
+                    set_tooltip_text: Some(match model.target {
+                        VolumeTarget::Master => "Controlling system volume",
+                        VolumeTarget::Mpd => "Controlling MPD volume",
+                    }),
+                    connect_clicked[sender] => move |_| {
+                        sender.input(VolumeEvents::ToggleTarget);
+                    }
+                },
+
+                model.outputs.widget(),
             }
         }
     }
 
     fn init(
-        _: Self::Init,
+        init: Self::Init,
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> relm4::ComponentParts<Self> {
-        let model = VolumeModel {};
+        let mut master = volume::detect();
+        if crate::is_verbose() {
+            println!("Using the {} volume backend", master.name());
+        }
+        let mut mpd = MpdBackend::new();
+        // Both targets are watched so that switching targets doesn't stop the
+        // slider from following outside changes.
+        let master_subscribed = subscribe(master.as_mut(), &sender);
+        let master = Rc::new(RefCell::new(master));
+        let mpd_subscribed = subscribe(&mut mpd, &sender);
+        if !master_subscribed || !mpd_subscribed {
+            glib::timeout_add_local(
+                Duration::from_secs(1),
+                clone!(@strong sender => move || {
+                    sender.input(VolumeEvents::Refresh);
+                    ControlFlow::Continue
+                }),
+            );
+        }
+        let outputs = OutputModel::builder().launch(master.clone()).detach();
+        let mut model = VolumeModel {
+            master,
+            mpd,
+            target: init.target,
+            volume: 0.,
+            muted: false,
+            scroll_step: init.scroll_step,
+            show_percentage: init.show_percentage,
+            outputs,
+        };
+        model.refresh();
         let widgets = view_output!();
         ComponentParts { model, widgets }
     }
 
     fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
-        match message {
-            VolumeEvents::Change(volume) => set_volume(volume),
+        let result = match message {
+            VolumeEvents::Change(volume) => {
+                self.volume = volume.clamp(0., 100.);
+                let volume = self.volume;
+                self.with_backend(|backend| backend.set_volume(volume))
+            }
+            VolumeEvents::Scroll(dy) => {
+                self.volume = (self.volume - dy * self.scroll_step).clamp(0., 100.);
+                let volume = self.volume;
+                self.with_backend(|backend| backend.set_volume(volume))
+            }
+            VolumeEvents::ToggleMute => {
+                self.muted = !self.muted;
+                let muted = self.muted;
+                self.with_backend(|backend| backend.set_muted(muted))
+            }
+            VolumeEvents::ToggleTarget => {
+                self.target = match self.target {
+                    VolumeTarget::Master => VolumeTarget::Mpd,
+                    VolumeTarget::Mpd => VolumeTarget::Master,
+                };
+                self.refresh();
+                Ok(())
+            }
+            VolumeEvents::Refresh => {
+                self.refresh();
+                Ok(())
+            }
+            VolumeEvents::Remote(volume, reply) => {
+                let result = match volume {
+                    Some(volume) => {
+                        let volume = volume.clamp(0., 100.);
+                        self.with_backend(|backend| backend.set_volume(volume))
+                    }
+                    None => Ok(()),
+                };
+                self.refresh();
+                let _ = reply.send(result.clone().map(|_| self.volume));
+                result
+            }
+        };
+        if let Err(error) = result {
+            println!(
+                "Error while changing volume with {}: {}",
+                self.with_backend(|backend| backend.name()),
+                error
+            );
         }
     }
 }
 
-fn get_current_volume() -> f64 {
-    let output = Command::new("pamixer")
-        .arg("--get-volume-human")
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Failed to execute pamixer command");
-    let output_piped = Command::new("tr")
-        .arg("-d")
-        .arg("%")
-        .stdin(Stdio::from(
-            output.stdout.expect("Failed to get ouput of pamixer"),
-        ))
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Failed to execute pr command");
-    let output = output_piped
-        .wait_with_output()
-        .expect("Failed to get pr output");
-    let result = std::str::from_utf8(&output.stdout)
-        .expect("Failed to convert output of pr to string")
-        .trim();
-    result.parse::<f64>().expect("Couldn't parse output")
+impl VolumeModel {
+    fn with_backend<T>(&mut self, action: impl FnOnce(&mut dyn VolumeBackend) -> T) -> T {
+        match self.target {
+            VolumeTarget::Master => action(self.master.borrow_mut().as_mut()),
+            VolumeTarget::Mpd => action(&mut self.mpd),
+        }
+    }
+
+    /// Reads the volume and mute state back from the active backend.
+    fn refresh(&mut self) {
+        let (name, volume, muted) =
+            self.with_backend(|backend| (backend.name(), backend.volume(), backend.is_muted()));
+        match volume {
+            Ok(volume) => self.volume = volume,
+            Err(error) => println!("Error while reading volume from {}: {}", name, error),
+        }
+        self.muted = muted.unwrap_or(false);
+    }
 }
 
-fn set_volume(volume: f64) {
-    let _output = Command::new("amixer")
-        .arg("-D")
-        .arg("default")
-        .arg("sset")
-        .arg("Master")
-        .arg(&format!("{}%", volume.to_string()))
-        .output()
-        .unwrap();
+fn subscribe(backend: &mut dyn VolumeBackend, sender: &ComponentSender<VolumeModel>) -> bool {
+    let input = sender.input_sender().clone();
+    backend.subscribe(Box::new(move || input.emit(VolumeEvents::Refresh)))
 }
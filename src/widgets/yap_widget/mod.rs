@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use gtk::gio;
+use gtk::glib::clone;
 use gtk::prelude::*;
 use gtk::traits::OrientableExt;
 use gtk4_layer_shell::{Layer, LayerShell};
@@ -9,11 +11,15 @@ use relm4::{
     SimpleComponent,
 };
 
-use super::control_widget::ControlModel;
-use super::music_widget::MusicModel;
+use super::control_widget::{ControlLayout, ControlModel};
+use super::music_widget::{MusicEvents, MusicModel, MusicOutput};
 use super::power_menu_widget::PowerMenuModel;
 use super::queue_widget::QueueModel;
-use super::volume_widget::VolumeModel;
+use super::toast_widget::{ToastEvents, ToastModel, ToastOutput, UndoAction};
+use super::volume_widget::{VolumeEvents, VolumeModel, VolumeReply};
+use crate::remote;
+use crate::web;
+use crate::Config;
 
 pub struct YapModel {
     volume: Controller<VolumeModel>,
@@ -21,18 +27,30 @@ pub struct YapModel {
     controls: Controller<ControlModel>,
     queue: Controller<QueueModel>,
     power: Controller<PowerMenuModel>,
+    toast: Controller<ToastModel>,
+    window: gtk::Window,
 }
 
 #[derive(Debug)]
 pub enum YapEvents {
     StartWidget,
+    Undoable(UndoAction),
+    Undo(UndoAction),
+    Error(String),
+    Show,
+    Hide,
+    ToggleVisible,
+    Navigate(String),
+    Search(String),
+    /// Reads the volume, or sets it first, for a remote client.
+    Volume(Option<f64>, VolumeReply),
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for YapModel {
     type Input = YapEvents;
     type Output = ();
-    type Init = String;
+    type Init = Config;
 
     view! {
         gtk::Window {
@@ -74,6 +92,8 @@ impl SimpleComponent for YapModel {
 
                     model.music.widget(),
                 },
+
+                model.toast.widget(),
             }
         }
     }
@@ -83,18 +103,7 @@ impl SimpleComponent for YapModel {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let provider = gtk::CssProvider::new();
-        provider.load_from_path(
-            Path::new("src")
-                .join("widgets")
-                .join("yap_widget")
-                .join("main.css"),
-        );
-        gtk::style_context_add_provider_for_display(
-            &gtk::gdk::Display::default().expect("Error initializing css provider."),
-            &provider,
-            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
-        );
+        load_css();
         // let header = gtk::Box::builder()
         //     .orientation(gtk::Orientation::Horizontal)
         //     .hexpand(true)
@@ -112,27 +121,40 @@ impl SimpleComponent for YapModel {
         // let window = gtk::Box::builder()
         //     .orientation(gtk::Orientation::Vertical)
         //     .build();
-        root.init_layer_shell();
-        root.set_default_size(1600, 600);
-        root.set_layer(Layer::Overlay);
-        root.set_anchor(gtk4_layer_shell::Edge::Top, true);
-        root.set_anchor(gtk4_layer_shell::Edge::Bottom, false);
-        root.set_exclusive_zone(1080);
+        if !init.runtime.window_mode {
+            root.init_layer_shell();
+            root.set_default_size(1600, 600);
+            root.set_layer(Layer::Overlay);
+            root.set_anchor(gtk4_layer_shell::Edge::Top, true);
+            root.set_anchor(gtk4_layer_shell::Edge::Bottom, false);
+            root.set_exclusive_zone(1080);
+        }
+        add_visibility_actions(root);
+        let web_settings = init.web.clone();
         let volume_widget = VolumeModel::builder()
-            .launch(())
+            .launch(init.volume.clone())
             .forward(sender.input_sender(), |()| YapEvents::StartWidget);
         let control_widget = ControlModel::builder()
-            .launch(())
-            .forward(sender.input_sender(), |()| YapEvents::StartWidget);
-        let music_widget = MusicModel::builder()
-            .launch(init)
-            .forward(sender.input_sender(), |()| YapEvents::StartWidget);
+            .launch((init.clone(), ControlLayout::Full))
+            .forward(sender.input_sender(), |_| YapEvents::StartWidget);
+        let music_widget =
+            MusicModel::builder()
+                .launch(init.clone())
+                .forward(sender.input_sender(), |output| match output {
+                    MusicOutput::Undoable(action) => YapEvents::Undoable(action),
+                    MusicOutput::Error(error) => YapEvents::Error(error),
+                });
         let queue_widget = QueueModel::builder()
             .launch(())
-            .forward(sender.input_sender(), |()| YapEvents::StartWidget);
+            .forward(sender.input_sender(), YapEvents::Undoable);
         let power_menu_widget = PowerMenuModel::builder()
-            .launch(())
+            .launch(init)
             .forward(sender.input_sender(), |()| YapEvents::StartWidget);
+        let toast_widget = ToastModel::builder()
+            .launch(())
+            .forward(sender.input_sender(), |output| match output {
+                ToastOutput::Undo(action) => YapEvents::Undo(action),
+            });
         // header.append(power_menu_widget.widget());
         // volume_queue.append(volume_widget.widget());
         // volume_queue.append(queue_widget.widget());
@@ -149,10 +171,70 @@ impl SimpleComponent for YapModel {
             controls: control_widget,
             queue: queue_widget,
             power: power_menu_widget,
+            toast: toast_widget,
+            window: root.clone(),
         };
+        remote::serve(sender.input_sender().clone());
+        if web_settings.enabled {
+            web::serve(&web_settings, sender.input_sender().clone());
+        }
         let widgets = view_output!();
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, _message: Self::Input, _sender: ComponentSender<Self>) {}
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            YapEvents::StartWidget => {}
+            YapEvents::Undoable(action) => self.toast.emit(ToastEvents::Push(action)),
+            YapEvents::Error(error) => self.toast.emit(ToastEvents::Error(error)),
+            YapEvents::Undo(action) => match action {
+                UndoAction::DeleteSong(song) => self.music.emit(MusicEvents::RestoreSong(song)),
+                UndoAction::ClearQueue(state) => {
+                    state.restore();
+                    state.save();
+                }
+                UndoAction::RemoveFromQueue(entry) => entry.restore(),
+            },
+            YapEvents::Show => self.window.set_visible(true),
+            YapEvents::Hide => self.window.set_visible(false),
+            YapEvents::ToggleVisible => self.window.set_visible(!self.window.is_visible()),
+            YapEvents::Navigate(page) => self.music.emit(MusicEvents::Navigate(page)),
+            YapEvents::Search(query) => self.music.emit(MusicEvents::Search(query)),
+            YapEvents::Volume(volume, reply) => {
+                self.volume.emit(VolumeEvents::Remote(volume, reply))
+            }
+        }
+    }
+}
+
+pub fn load_css() {
+    let provider = gtk::CssProvider::new();
+    provider.load_from_path(
+        Path::new("src")
+            .join("widgets")
+            .join("yap_widget")
+            .join("main.css"),
+    );
+    gtk::style_context_add_provider_for_display(
+        &gtk::gdk::Display::default().expect("Error initializing css provider."),
+        &provider,
+        gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+}
+
+/// Registers the `show`, `hide` and `toggle` application actions, which
+/// `yap-utils show|hide|toggle` activate remotely.
+fn add_visibility_actions(window: &gtk::Window) {
+    let app = relm4::main_application();
+    let show = gio::SimpleAction::new("show", None);
+    show.connect_activate(clone!(@weak window => move |_, _| window.set_visible(true)));
+    let hide = gio::SimpleAction::new("hide", None);
+    hide.connect_activate(clone!(@weak window => move |_, _| window.set_visible(false)));
+    let toggle = gio::SimpleAction::new("toggle", None);
+    toggle.connect_activate(clone!(@weak window => move |_, _| {
+        window.set_visible(!window.is_visible())
+    }));
+    app.add_action(&show);
+    app.add_action(&hide);
+    app.add_action(&toggle);
 }
@@ -1,8 +1,12 @@
 use std::process::Command;
 
+use serde::{Deserialize, Serialize};
+
+use crate::mpd;
+
 pub struct Yap {}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Song {
     pub name: String,
     pub artist: String,
@@ -34,6 +38,10 @@ impl PartialEq for Time {
 }
 
 impl Time {
+    pub fn remaining_seconds(&self) -> u32 {
+        (self.tot_min * 60 + self.tot_sec).saturating_sub(self.min * 60 + self.sec)
+    }
+
     fn from_str(s: &str) -> Option<Time> {
         let parts: Vec<&str> = s
             .split(|c| c == ':' || c == '/' || c == '(' || c == '%' || c == ')')
@@ -61,19 +69,82 @@ impl Time {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct QueueEntry {
+    pub position: u32,
+    pub id: u32,
+    /// Duration of the song in seconds.
+    pub duration: u32,
+    pub song: Song,
+}
+
+impl QueueEntry {
+    fn from_str(s: &str) -> Option<QueueEntry> {
+        let mut parts = s.splitn(4, '\t');
+        let position = parts.next()?.trim().parse().ok()?;
+        let id = parts.next()?.trim().parse().ok()?;
+        let duration = parts.next()?.trim().parse().ok()?;
+        let (name, artist) = parts.next()?.split_once(" - ")?;
+
+        Some(QueueEntry {
+            position,
+            id,
+            duration,
+            song: Song {
+                name: name.to_string(),
+                artist: artist.to_string(),
+            },
+        })
+    }
+}
+
 pub struct Status {
     pub repeat: bool,
     pub random: bool,
     pub is_paused: bool,
+    pub position: Option<u32>,
 }
 
+/// Flags the GUI passes to `yap` that older yap releases don't have, by
+/// subcommand. The `Position` key of `yap mpd status` comes with the same
+/// releases; without it the current queue entry simply isn't highlighted.
+const REQUIRED_FLAGS: [(&[&str], &str); 7] = [
+    (&["mpd", "queue"], "--positions"),
+    (&["mpd", "queue"], "--durations"),
+    (&["mpd", "queue-save"], "--name"),
+    (&["mpd", "queue-remove"], "--position"),
+    (&["mpd", "queue-move"], "--from"),
+    (&["mpd", "play"], "--position"),
+    (&["song", "edit"], "--new-name"),
+];
+
 impl Yap {
+    /// Starts a `yap` invocation that talks to the MPD server the GUI uses.
+    fn command() -> Command {
+        Yap::program("yap")
+    }
+
+    fn program(program: &str) -> Command {
+        let mut command = Command::new(program);
+        mpd::use_selected_server(&mut command);
+        command
+    }
+
+    /// Returns the required flags that the installed `yap` doesn't list in
+    /// its `--help`, as the command lines that would fail.
+    pub fn missing_flags() -> Vec<String> {
+        REQUIRED_FLAGS
+            .iter()
+            .filter(|(subcommand, flag)| {
+                let help = Yap::command().args(*subcommand).arg("--help").output();
+                !help.is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(flag))
+            })
+            .map(|(subcommand, flag)| format!("yap {} {}", subcommand.join(" "), flag))
+            .collect()
+    }
+
     pub fn get_playlists() -> Vec<String> {
-        let command = Command::new("yap")
-            .arg("playlist")
-            .arg("list")
-            .output()
-            .unwrap();
+        let command = Yap::command().arg("playlist").arg("list").output().unwrap();
         let output = String::from_utf8_lossy(&command.stdout);
         output
             .lines()
@@ -83,11 +154,7 @@ impl Yap {
     }
 
     pub fn get_songs() -> Vec<Song> {
-        let command = Command::new("yap")
-            .arg("song")
-            .arg("list")
-            .output()
-            .unwrap();
+        let command = Yap::command().arg("song").arg("list").output().unwrap();
         let output = String::from_utf8_lossy(&command.stdout);
         output
             .lines()
@@ -103,7 +170,7 @@ impl Yap {
     }
 
     pub fn play_playlist(playlist: &str) {
-        let _command = Command::new("yap")
+        let _command = Yap::command()
             .arg("play")
             .arg("playlist")
             .arg("--name")
@@ -113,7 +180,7 @@ impl Yap {
     }
 
     pub fn play_song(song: &str) {
-        let _command = Command::new("yap")
+        let _command = Yap::command()
             .arg("play")
             .arg("song")
             .arg("--name")
@@ -122,12 +189,34 @@ impl Yap {
             .unwrap();
     }
 
+    pub fn play_next(song: &str) {
+        Yap::add_to_queue(song);
+        let queue = Yap::get_queue();
+        let current_position = Yap::status().ok().and_then(|status| status.position);
+        if let (Some(last), Some(current)) = (queue.last(), current_position) {
+            if last.position > current + 1 {
+                Yap::move_in_queue(last.position, current + 1);
+            }
+        }
+    }
+
+    pub fn play_now(song: &str) {
+        match Yap::status().ok().and_then(|status| status.position) {
+            Some(current) => {
+                Yap::play_next(song);
+                Yap::play_at(current + 1);
+            }
+            None => {
+                Yap::add_to_queue(song);
+                if let Some(last) = Yap::get_queue().last() {
+                    Yap::play_at(last.position);
+                }
+            }
+        }
+    }
+
     pub fn current() -> Option<(Song, Time)> {
-        let command = Command::new("yap")
-            .arg("mpd")
-            .arg("current")
-            .output()
-            .unwrap();
+        let command = Yap::command().arg("mpd").arg("current").output().unwrap();
         let output = String::from_utf8_lossy(&command.stdout);
 
         let lines: Vec<&str> = output.lines().collect();
@@ -149,7 +238,7 @@ impl Yap {
     }
 
     pub fn seek(percentage: u64) {
-        let _command = Command::new("yap")
+        let _command = Yap::command()
             .arg("mpd")
             .arg("seek")
             .arg("--percentage")
@@ -159,15 +248,12 @@ impl Yap {
     }
 
     pub fn status() -> Result<Status, String> {
-        let command = Command::new("yap")
-            .arg("mpd")
-            .arg("status")
-            .output()
-            .unwrap();
+        let command = Yap::command().arg("mpd").arg("status").output().unwrap();
         let output = String::from_utf8_lossy(&command.stdout);
         let mut pause = false;
         let mut random = false;
         let mut repeat = false;
+        let mut position = None;
 
         for part in output.split('\t') {
             let mut iter = part.split(':');
@@ -178,6 +264,7 @@ impl Yap {
                         "Pause" => pause = value.trim().parse().map_err(|_| "Invalid bool")?,
                         "Random" => random = value.trim().parse().map_err(|_| "Invalid bool")?,
                         "Repeat" => repeat = value.trim().parse().map_err(|_| "Invalid bool")?,
+                        "Position" => position = value.trim().parse().ok(),
                         _ => return Err("Invalid key".to_string()),
                     }
                 } else {
@@ -192,92 +279,141 @@ impl Yap {
             is_paused: pause,
             random,
             repeat,
+            position,
         })
     }
 
     pub fn random() {
-        let _command = Command::new("yap")
-            .arg("mpd")
-            .arg("shuffle")
-            .output()
-            .unwrap();
+        let _command = Yap::command().arg("mpd").arg("shuffle").output().unwrap();
     }
 
     pub fn next() {
-        let _command = Command::new("yap").arg("mpd").arg("next").output().unwrap();
+        let _command = Yap::command().arg("mpd").arg("next").output().unwrap();
     }
 
     pub fn play() {
-        let _command = Command::new("yap").args(["mpd", "play"]).output().unwrap();
+        let _command = Yap::command().args(["mpd", "play"]).output().unwrap();
     }
 
     pub fn toggle_pause() {
-        let _command = Command::new("yap")
-            .arg("mpd")
-            .arg("pause")
-            .output()
-            .unwrap();
+        let _command = Yap::command().arg("mpd").arg("pause").output().unwrap();
     }
 
     pub fn prev() {
-        let _command = Command::new("yap")
-            .arg("mpd")
-            .arg("previous")
-            .output()
-            .unwrap();
+        let _command = Yap::command().arg("mpd").arg("previous").output().unwrap();
     }
 
     pub fn repeat() {
-        let _command = Command::new("yap")
-            .arg("mpd")
-            .arg("repeat")
-            .output()
-            .unwrap();
+        let _command = Yap::command().arg("mpd").arg("repeat").output().unwrap();
     }
 
     pub fn delete_song(song_name: &str) {
-        let _command = Command::new("yap")
+        let _command = Yap::command()
             .args(["song", "delete", "--name", song_name])
             .output()
             .unwrap();
     }
 
+    /// Renames `song_name` and sets its artist in the yap database.
+    pub fn edit_song(song_name: &str, song: &Song) -> Result<(), String> {
+        let output = Yap::command()
+            .args(["song", "edit", "--name", song_name])
+            .args(["--new-name", &song.name, "--artist", &song.artist])
+            .output()
+            .map_err(|error| error.to_string())?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    }
+
+    /// Whether `program` has the `song miniature --name` subcommand that
+    /// miniatures are fetched with.
+    pub fn supports_miniatures(program: &str) -> bool {
+        let help = Yap::program(program)
+            .args(["song", "miniature", "--help"])
+            .output();
+        help.is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("--name"))
+    }
+
+    /// Downloads the miniature of `song_name` into yap's miniature directory.
+    /// The GUI assumes a `song miniature --name` subcommand that not every yap
+    /// release has, so `program` is configurable through `miniature_command`
+    /// to point at a wrapper script instead.
+    pub fn fetch_miniature(program: &str, song_name: &str) -> Result<(), String> {
+        let output = Yap::program(program)
+            .args(["song", "miniature", "--name", song_name])
+            .output()
+            .map_err(|error| error.to_string())?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    }
+
     pub fn add_to_queue(song_name: &str) {
-        let _command = Command::new("yap")
+        let _command = Yap::command()
             .args(["mpd", "queue-add", "--song-name", song_name])
             .output()
             .unwrap();
     }
 
-    pub fn get_queue() -> Vec<Song> {
-        let command = Command::new("yap").args(["mpd", "queue"]).output().unwrap();
+    pub fn get_queue() -> Vec<QueueEntry> {
+        let command = Yap::command()
+            .args(["mpd", "queue", "--positions", "--durations"])
+            .output()
+            .unwrap();
         let output = String::from_utf8_lossy(&command.stdout);
         output
             .lines()
             .filter(|line| !line.trim().is_empty())
-            .map(|s| {
-                let parts: Vec<&str> = s.split(" - ").collect();
-                Song {
-                    name: parts[0].to_string(),
-                    artist: parts[1].to_string(),
-                }
-            })
+            .filter_map(QueueEntry::from_str)
             .collect()
     }
 
-    pub fn remove_from_queue(song_name: &str) {
-        let _command = Command::new("yap")
-            .args(["mpd", "queue-remove", "--song-name", song_name])
+    pub fn remove_from_queue(position: u32) {
+        let _command = Yap::command()
+            .args(["mpd", "queue-remove", "--position", &position.to_string()])
+            .output()
+            .unwrap();
+    }
+
+    pub fn move_in_queue(from: u32, to: u32) {
+        let _command = Yap::command()
+            .args([
+                "mpd",
+                "queue-move",
+                "--from",
+                &from.to_string(),
+                "--to",
+                &to.to_string(),
+            ])
+            .output()
+            .unwrap();
+    }
+
+    pub fn play_at(position: u32) {
+        let _command = Yap::command()
+            .args(["mpd", "play", "--position", &position.to_string()])
             .output()
             .unwrap();
     }
 
     pub fn clear_queue() {
-        let _command = Command::new("yap").args(["mpd", "clear"]).output().unwrap();
+        let _command = Yap::command().args(["mpd", "clear"]).output().unwrap();
+    }
+
+    pub fn save_queue_as_playlist(playlist: &str) {
+        let _command = Yap::command()
+            .args(["mpd", "queue-save", "--name", playlist])
+            .output()
+            .unwrap();
     }
 
     pub fn shuffle_queue() {
-        let _command = Command::new("yap")
+        let _command = Yap::command()
             .args(["mpd", "queue-shuffle"])
             .output()
             .unwrap();